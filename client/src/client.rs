@@ -1,33 +1,116 @@
 use log::info;
+use quotes::{StockQuote, StreamKind};
 use std::io;
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Write};
 use std::net::{SocketAddr, TcpStream};
 
-const BUFFER_SIZE: usize = 1024;
-
-pub(crate) fn sub(addr: SocketAddr, remote: SocketAddr, tickers: Vec<String>) -> io::Result<()> {
-    let mut stream = TcpStream::connect(remote)?;
-    let request = format!("STREAM udp://{} {}\r\n", addr, tickers.join(","));
-    info!("Sending request to {}: {}", remote, request);
-    stream.write_all(request.as_bytes())?;
-    stream.flush()?;
-    let mut buffer = [0u8; BUFFER_SIZE];
-    let size = stream.read(&mut buffer)?;
-    let buffer = &buffer[..size];
-    if size == 4 && buffer == b"OK\r\n" {
+const END_OF_LIST: &str = "END";
+const WARN_PREFIX: &str = "WARN ";
+
+/// Control connection to the server, kept open for the lifetime of the subscription
+pub(crate) struct Session {
+    remote: SocketAddr,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Session {
+    pub(crate) fn connect(remote: SocketAddr) -> io::Result<Self> {
+        let writer = TcpStream::connect(remote)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self {
+            remote,
+            reader,
+            writer,
+        })
+    }
+
+    pub(crate) fn sub(
+        &mut self,
+        addr: SocketAddr,
+        tickers: Vec<String>,
+        kind: StreamKind,
+    ) -> io::Result<()> {
+        let mut request = format!("STREAM udp://{} {}", addr, tickers.join(","));
+        if kind != StreamKind::TRADES {
+            request.push_str(&format!(" {}", kind));
+        }
+        let warnings = self.request(&format!("{}\r\n", request))?;
+        for warning in warnings {
+            eprintln!("Server warning: {}", warning);
+        }
         info!("Subscribed to: {}", tickers.join(","));
         Ok(())
-    } else {
-        Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            String::from_utf8_lossy(buffer),
-        ))
+    }
+
+    pub(crate) fn unsub(&mut self, addr: SocketAddr) -> io::Result<()> {
+        self.request(&format!("UNSUBSCRIBE udp://{}\r\n", addr))?;
+        info!("Unsubscribed from {}", self.remote);
+        Ok(())
+    }
+
+    /// Requests tickers available on the server
+    pub(crate) fn list(&mut self) -> io::Result<Vec<String>> {
+        self.request("LIST\r\n")?;
+        self.read_list()
+    }
+
+    /// Requests the latest quote of each ticker
+    pub(crate) fn snapshot(&mut self, tickers: &[String]) -> io::Result<Vec<StockQuote>> {
+        self.request(&format!("SNAPSHOT {}\r\n", tickers.join(",")))?;
+        self.read_list()?
+            .iter()
+            .map(|line| serde_json::from_str(line).map_err(io::Error::other))
+            .collect()
+    }
+
+    fn read_list(&mut self) -> io::Result<Vec<String>> {
+        let mut items = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed before end of list",
+                ));
+            }
+            let line = line.trim_end();
+            if line == END_OF_LIST {
+                return Ok(items);
+            }
+            items.push(line.to_string());
+        }
+    }
+
+    pub(crate) fn quit(&mut self) -> io::Result<()> {
+        self.request("QUIT\r\n")?;
+        Ok(())
+    }
+
+    /// Sends a request and waits for `OK`, returns `WARN` lines received before it
+    fn request(&mut self, request: &str) -> io::Result<Vec<String>> {
+        info!("Sending request to {}: {}", self.remote, request);
+        self.writer.write_all(request.as_bytes())?;
+        self.writer.flush()?;
+        let mut warnings = Vec::new();
+        loop {
+            let mut line = String::new();
+            self.reader.read_line(&mut line)?;
+            if line == "OK\r\n" {
+                return Ok(warnings);
+            }
+            match line.strip_prefix(WARN_PREFIX) {
+                Some(warning) => warnings.push(warning.trim_end().to_string()),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, line)),
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use quotes::Price;
     use std::io::Read;
     use std::net::TcpListener;
     use std::str::FromStr;
@@ -45,12 +128,140 @@ mod tests {
                 stream.write_all(b"OK\r\n").unwrap();
             }
         });
-        sub(
-            SocketAddr::from_str("127.0.0.1:9090").unwrap(),
-            SocketAddr::from_str("127.0.0.1:9876").unwrap(),
-            vec!["AAPL".to_string()],
-        )
-        .unwrap();
+        Session::connect(SocketAddr::from_str("127.0.0.1:9876").unwrap())
+            .unwrap()
+            .sub(
+                SocketAddr::from_str("127.0.0.1:9090").unwrap(),
+                vec!["AAPL".to_string()],
+                StreamKind::TRADES,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_sub_stream_kinds() {
+        let listener = TcpListener::bind("127.0.0.1:9881").unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                for expected in [
+                    "STREAM udp://127.0.0.1:9090 AAPL trades,quotes\r\n",
+                    "STREAM udp://127.0.0.1:9090 AAPL quotes,depth\r\n",
+                    "STREAM udp://127.0.0.1:9090 AAPL prints\r\n",
+                ] {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    assert_eq!(line, expected);
+                    stream.write_all(b"OK\r\n").unwrap();
+                }
+            }
+        });
+        let address = SocketAddr::from_str("127.0.0.1:9090").unwrap();
+        let mut session =
+            Session::connect(SocketAddr::from_str("127.0.0.1:9881").unwrap()).unwrap();
+        for kind in ["both", "quotes,depth", "prints"] {
+            session
+                .sub(address, vec!["AAPL".to_string()], kind.parse().unwrap())
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_unsub() {
+        let listener = TcpListener::bind("127.0.0.1:9877").unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                for expected in [
+                    "STREAM udp://127.0.0.1:9090 AAPL\r\n",
+                    "UNSUBSCRIBE udp://127.0.0.1:9090\r\n",
+                    "QUIT\r\n",
+                ] {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    assert_eq!(line, expected);
+                    stream.write_all(b"OK\r\n").unwrap();
+                }
+            }
+        });
+        let address = SocketAddr::from_str("127.0.0.1:9090").unwrap();
+        let mut session =
+            Session::connect(SocketAddr::from_str("127.0.0.1:9877").unwrap()).unwrap();
+        session
+            .sub(address, vec!["AAPL".to_string()], StreamKind::TRADES)
+            .unwrap();
+        session.unsub(address).unwrap();
+        session.quit().unwrap();
+    }
+
+    #[test]
+    fn test_sub_with_warning() {
+        let listener = TcpListener::bind("127.0.0.1:9879").unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut buffer = [0u8; 1024];
+                let size = stream.read(&mut buffer).unwrap();
+                assert_eq!(
+                    &buffer[..size],
+                    b"STREAM udp://127.0.0.1:9090 AAPL,APPL\r\n"
+                );
+                stream
+                    .write_all(b"WARN UNKNOWN_TICKER APPL\r\nOK\r\n")
+                    .unwrap();
+            }
+        });
+        let mut session =
+            Session::connect(SocketAddr::from_str("127.0.0.1:9879").unwrap()).unwrap();
+        let warnings = session
+            .request("STREAM udp://127.0.0.1:9090 AAPL,APPL\r\n")
+            .unwrap();
+        assert_eq!(warnings, vec!["UNKNOWN_TICKER APPL"]);
+    }
+
+    #[test]
+    fn test_list() {
+        let listener = TcpListener::bind("127.0.0.1:9878").unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut buffer = [0u8; 1024];
+                let size = stream.read(&mut buffer).unwrap();
+                assert_eq!(&buffer[..size], b"LIST\r\n");
+                stream.write_all(b"OK\r\nAAPL\r\nNFLX\r\nEND\r\n").unwrap();
+            }
+        });
+        let mut session =
+            Session::connect(SocketAddr::from_str("127.0.0.1:9878").unwrap()).unwrap();
+        assert_eq!(session.list().unwrap(), vec!["AAPL", "NFLX"]);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let stock = StockQuote::new("AAPL", Price::from_cents(200), 3000000);
+        let json = serde_json::to_string(&stock).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:9880").unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut buffer = [0u8; 1024];
+                let size = stream.read(&mut buffer).unwrap();
+                assert_eq!(&buffer[..size], b"SNAPSHOT AAPL,NFLX\r\n");
+                stream
+                    .write_all(format!("OK\r\n{}\r\nEND\r\n", json).as_bytes())
+                    .unwrap();
+            }
+        });
+        let mut session =
+            Session::connect(SocketAddr::from_str("127.0.0.1:9880").unwrap()).unwrap();
+        let snapshot = session
+            .snapshot(&["AAPL".to_string(), "NFLX".to_string()])
+            .unwrap();
+        assert_eq!(snapshot, vec![stock]);
     }
 
     #[test]
@@ -66,11 +277,13 @@ mod tests {
                 stream.write_all(b"ERR: invalid input\r\n").unwrap();
             }
         });
-        sub(
-            SocketAddr::from_str("127.0.0.1:9090").unwrap(),
-            SocketAddr::from_str("127.0.0.1:5657").unwrap(),
-            vec!["AAPL".to_string()],
-        )
-        .unwrap();
+        Session::connect(SocketAddr::from_str("127.0.0.1:5657").unwrap())
+            .unwrap()
+            .sub(
+                SocketAddr::from_str("127.0.0.1:9090").unwrap(),
+                vec!["AAPL".to_string()],
+                StreamKind::TRADES,
+            )
+            .unwrap();
     }
 }
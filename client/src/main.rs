@@ -2,12 +2,16 @@ mod client;
 mod monitor;
 mod server;
 
+use crate::client::Session;
 use crate::server::Server;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use log::{error, warn};
+use quotes::{CorporateActionKind, Level, MarketEvent, OrderBook, StreamKind};
+use std::collections::HashMap;
 use std::net::{SocketAddr, UdpSocket};
 use std::path::PathBuf;
 use std::process::exit;
-use std::sync::mpsc;
+use std::sync::{Mutex, PoisonError, mpsc};
 use std::{fs, io, thread};
 
 #[derive(Parser)]
@@ -21,18 +25,49 @@ struct Cli {
     #[clap(long)]
     #[arg(default_value = "resources/sub.txt")]
     tickers: PathBuf,
+    /// Print the latest quote of each ticker before the stream starts
+    #[clap(long)]
+    snapshot: bool,
+    /// Market data to stream, a comma separated list of trades, quotes, both, depth and prints
+    #[clap(long)]
+    #[arg(default_value = "trades")]
+    stream: StreamKind,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print tickers available on the server
+    List,
 }
 
 fn main() {
     let cli = Cli::parse();
     env_logger::init();
-    if let Err(e) = start(cli.remote_addr, cli.local_addr, cli.tickers) {
+    let result = match cli.command {
+        Some(Command::List) => list(cli.remote_addr),
+        None => start(
+            cli.remote_addr,
+            cli.local_addr,
+            cli.tickers,
+            cli.snapshot,
+            cli.stream,
+        ),
+    };
+    if let Err(e) = result {
         eprintln!("{}", e);
         exit(-1);
     }
 }
 
-fn start(remote_addr: SocketAddr, local_addr: SocketAddr, tickers: PathBuf) -> io::Result<()> {
+fn start(
+    remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+    tickers: PathBuf,
+    snapshot: bool,
+    kind: StreamKind,
+) -> io::Result<()> {
     let tickers = load_tickers(tickers)?;
 
     let socket = UdpSocket::bind(local_addr)?;
@@ -41,16 +76,90 @@ fn start(remote_addr: SocketAddr, local_addr: SocketAddr, tickers: PathBuf) -> i
     let (stop_tx, stop_rx) = mpsc::channel();
 
     let (addr_tx, pong_tx) = monitor::run(socket.try_clone()?, stop_tx.clone());
+    let mut session = Session::connect(remote_addr)?;
+    if snapshot {
+        for stock in session.snapshot(&tickers)? {
+            let _ = stock_tx.send(MarketEvent::Quote(stock));
+        }
+    }
     Server::run(socket.try_clone()?, addr_tx, stock_tx, pong_tx, stop_tx)?;
 
-    client::sub(local_addr, remote_addr, tickers)?;
+    session.sub(local_addr, tickers, kind)?;
+    let session = Mutex::new(session);
+    ctrlc::set_handler(move || {
+        let mut session = session.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(e) = session.unsub(local_addr).and_then(|_| session.quit()) {
+            error!("Failed to unsubscribe: {}", e);
+        }
+        exit(0);
+    })
+    .map_err(io::Error::other)?;
 
     thread::spawn(move || {
-        while let Ok(stock) = stock_rx.recv() {
-            println!(
-                "[{}] price: {} volume: {} timestamp: {}",
-                stock.ticker, stock.price, stock.volume, stock.timestamp
-            );
+        let mut books: HashMap<String, OrderBook> = HashMap::new();
+        while let Ok(message) = stock_rx.recv() {
+            match message {
+                MarketEvent::Quote(stock) => println!(
+                    "[{}] price: {} volume: {} timestamp: {}",
+                    stock.ticker, stock.price, stock.volume, stock.timestamp
+                ),
+                MarketEvent::Trade(trade) => {
+                    let conditions: Vec<String> = trade
+                        .conditions
+                        .iter()
+                        .map(|condition| format!(" {}", condition))
+                        .collect();
+                    println!(
+                        "[{}] trade #{}: {} @ {} aggressor: {}{} timestamp: {}",
+                        trade.ticker,
+                        trade.trade_id,
+                        trade.size,
+                        trade.price,
+                        trade.aggressor,
+                        conditions.concat(),
+                        trade.timestamp
+                    )
+                }
+                MarketEvent::Book(book) => println!(
+                    "[{}] bid: {} x {} ask: {} x {} timestamp: {}",
+                    book.ticker, book.bid, book.bid_size, book.ask, book.ask_size, book.timestamp
+                ),
+                MarketEvent::DepthSnapshot(snapshot) => {
+                    let book = books
+                        .entry(snapshot.ticker.clone())
+                        .or_insert_with(|| OrderBook::new(&snapshot.ticker));
+                    match book.apply_snapshot(&snapshot) {
+                        Ok(true) => print_depth(book),
+                        Ok(false) => {}
+                        Err(e) => warn!("Skipped depth snapshot: {}", e),
+                    }
+                }
+                MarketEvent::Depth(update) => {
+                    let book = books
+                        .entry(update.ticker.clone())
+                        .or_insert_with(|| OrderBook::new(&update.ticker));
+                    match book.apply(&update) {
+                        Ok(true) => print_depth(book),
+                        Ok(false) => {}
+                        Err(e) => warn!("{}, waiting for the next snapshot", e),
+                    }
+                }
+                MarketEvent::Status(status) => println!(
+                    "[{}] status: {} timestamp: {}",
+                    status.ticker.as_deref().unwrap_or("MARKET"),
+                    status.phase,
+                    status.timestamp
+                ),
+                MarketEvent::Action(action) => {
+                    if let CorporateActionKind::Rename { .. } = action.kind {
+                        books.remove(&action.ticker);
+                    }
+                    println!(
+                        "[{}] {} timestamp: {}",
+                        action.ticker, action.kind, action.timestamp
+                    )
+                }
+            }
         }
     });
     if let Ok(error_msg) = stop_rx.recv() {
@@ -60,6 +169,31 @@ fn start(remote_addr: SocketAddr, local_addr: SocketAddr, tickers: PathBuf) -> i
     Ok(())
 }
 
+fn print_depth(book: &OrderBook) {
+    let levels = |levels: Vec<Level>| {
+        let levels: Vec<String> = levels
+            .iter()
+            .map(|level| format!("{} x {}", level.price, level.size))
+            .collect();
+        levels.join(", ")
+    };
+    println!(
+        "[{}] depth #{} bids: {} asks: {}",
+        book.ticker(),
+        book.sequence().unwrap_or_default(),
+        levels(book.bids().collect()),
+        levels(book.asks().collect())
+    );
+}
+
+fn list(remote_addr: SocketAddr) -> io::Result<()> {
+    let mut session = Session::connect(remote_addr)?;
+    for ticker in session.list()? {
+        println!("{}", ticker);
+    }
+    session.quit()
+}
+
 fn load_tickers(path: PathBuf) -> io::Result<Vec<String>> {
     let data = fs::read_to_string(path)?;
     Ok(quotes::parse_tickers(&data))
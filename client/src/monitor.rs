@@ -1,4 +1,5 @@
 use log::{error, info};
+use quotes::{Envelope, Message};
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
@@ -37,8 +38,13 @@ impl PingPongMonitor {
                 }
                 return;
             };
-            loop {
-                if socket.send_to(b"PING", addr).is_err() {
+            for sequence in 1.. {
+                let ping = Envelope::new(sequence, Message::Ping);
+                let Ok(ping) = serde_json::to_vec(&ping) else {
+                    error!("Failed to serialize PING");
+                    break;
+                };
+                if socket.send_to(&ping, addr).is_err() {
                     if stop_tx
                         .send("Failed to send 'PING' UDP socket is closed!".to_string())
                         .is_err()
@@ -88,9 +94,10 @@ mod tests {
         addr_tx
             .send(SocketAddr::from_str("127.0.0.1:9191").unwrap())
             .unwrap();
-        let mut buffer = [0u8; 4];
-        server.recv(&mut buffer).unwrap();
-        assert_eq!("PING", std::str::from_utf8(&buffer).unwrap());
+        let mut buffer = [0u8; 256];
+        let len = server.recv(&mut buffer).unwrap();
+        let ping = Envelope::parse(&buffer[..len]).unwrap();
+        assert_eq!(ping, Envelope::new(1, Message::Ping));
     }
 
     #[test]
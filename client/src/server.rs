@@ -1,15 +1,15 @@
-use log::{error, info};
-use quotes::StockQuote;
+use log::{debug, error, info, warn};
+use quotes::{Envelope, MarketEvent, Message, RESTART_DROP};
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::mpsc::Sender;
 use std::{io, thread};
 
-const BUFFER_SIZE: usize = 1024;
-const PONG_SIZE: usize = 4;
+/// The largest UDP payload, depth snapshots outgrow smaller buffers
+const BUFFER_SIZE: usize = 65536;
 
 pub(crate) struct Server {
     addr_tx: Sender<SocketAddr>,
-    stock_tx: Sender<StockQuote>,
+    stock_tx: Sender<MarketEvent>,
     pong_tx: Sender<()>,
     socket: UdpSocket,
     stop_tx: Sender<String>,
@@ -19,7 +19,7 @@ impl Server {
     pub(crate) fn run(
         socket: UdpSocket,
         addr_tx: Sender<SocketAddr>,
-        stock_tx: Sender<StockQuote>,
+        stock_tx: Sender<MarketEvent>,
         pong_tx: Sender<()>,
         stop_tx: Sender<String>,
     ) -> io::Result<()> {
@@ -34,7 +34,7 @@ impl Server {
     fn new(
         socket: UdpSocket,
         addr_tx: Sender<SocketAddr>,
-        stock_tx: Sender<StockQuote>,
+        stock_tx: Sender<MarketEvent>,
         pong_tx: Sender<()>,
         stop_tx: Sender<String>,
     ) -> io::Result<Self> {
@@ -50,6 +50,7 @@ impl Server {
     fn start(&self) {
         let mut buffer = [0u8; BUFFER_SIZE];
         let mut address_sent = false;
+        let mut sequence = Sequence::default();
         while let Ok((size, addr)) = self.socket.recv_from(&mut buffer) {
             if !address_sent {
                 if self.addr_tx.send(addr).is_err() {
@@ -59,26 +60,71 @@ impl Server {
                 }
                 address_sent = true;
             }
-            if size == PONG_SIZE && String::from_utf8_lossy(&buffer[..PONG_SIZE]).eq("PONG") {
-                info!("Received PONG from {}", addr);
-                let _ = self.pong_tx.send(());
-            } else {
-                let Ok(stock) = serde_json::from_slice(&buffer[..size]) else {
-                    error!("Invalid UDP packet!");
+            let envelope = match Envelope::parse(&buffer[..size]) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    error!("Invalid UDP packet: {}", e);
                     continue;
-                };
-                if self.stock_tx.send(stock).is_err() {
-                    error!("Stock channel is closed!");
-                    break;
                 }
+            };
+            match envelope.message {
+                Message::Pong => {
+                    info!("Received PONG from {}", addr);
+                    let _ = self.pong_tx.send(());
+                }
+                Message::Ping => {}
+                Message::Event(event) => {
+                    sequence.receive(envelope.sequence);
+                    if self.stock_tx.send(event).is_err() {
+                        error!("Stock channel is closed!");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Numbers of market data envelopes seen so far
+#[derive(Default)]
+struct Sequence {
+    /// The highest number of the current numbering
+    last: Option<u64>,
+}
+
+impl Sequence {
+    /// Reports the envelopes skipped before `sequence`. Reordered envelopes are not
+    /// losses, a big drop or a new first number means the server restarted
+    fn receive(&mut self, sequence: u64) -> u64 {
+        let Some(last) = self.last else {
+            self.last = Some(sequence);
+            return 0;
+        };
+        if sequence > last {
+            self.last = Some(sequence);
+            let lost = sequence - last - 1;
+            if lost > 0 {
+                warn!("Lost {} messages", lost);
             }
+            return lost;
         }
+        if sequence == 1 || last - sequence >= RESTART_DROP {
+            info!("Message numbers restarted at {} after {}", sequence, last);
+            self.last = Some(sequence);
+        } else {
+            debug!("Message {} arrived after {}", sequence, last);
+        }
+        0
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use quotes::{
+        BookQuote, CorporateAction, DepthSnapshot, DepthUpdate, Level, MarketStatus, OrderBook,
+        Price, Side, StockQuote, Trade, TradingPhase,
+    };
     use std::sync::mpsc;
 
     #[test]
@@ -94,17 +140,65 @@ mod tests {
 
         let sender_addr = SocketAddr::from(([127, 0, 0, 1], 9459));
         let socket = UdpSocket::bind(sender_addr).unwrap();
-        socket.send_to(b"PONG", address).unwrap();
+        let pong = serde_json::to_vec(&Envelope::new(1, Message::Pong)).unwrap();
+        socket.send_to(&pong, address).unwrap();
 
         let received_addr = addr_rx.recv().unwrap();
         assert_eq!(sender_addr, received_addr);
 
+        #[allow(clippy::let_unit_value)]
         let _ = pong_rx.recv().unwrap();
 
-        let stock = StockQuote::new("AAPL", 200, 3000000);
-        let stock_json = serde_json::to_vec(&stock).unwrap();
-        socket.send_to(&stock_json, address).unwrap();
-        let received_stock = stock_rx.recv().unwrap();
-        assert_eq!(received_stock, stock);
+        let (bid, ask) = (Price::from_cents(199), Price::from_cents(201));
+        let level = |price, size| Level { price, size };
+        let mut depth = OrderBook::new("AAPL");
+        let snapshot = DepthSnapshot {
+            ticker: "AAPL".to_string(),
+            sequence: 7,
+            bids: vec![level(bid, 300)],
+            asks: vec![level(ask, 500)],
+            timestamp: 1,
+        };
+        depth.apply_snapshot(&snapshot).unwrap();
+        let update = DepthUpdate {
+            ticker: "AAPL".to_string(),
+            sequence: 8,
+            changes: depth.changes_to(&[level(bid, 100)], &[]),
+            timestamp: 2,
+        };
+        let events = [
+            MarketEvent::Quote(StockQuote::new("AAPL", Price::from_cents(200), 3000000)),
+            MarketEvent::Trade(Trade::new("AAPL", 1, bid, 300, Side::Ask, 1)),
+            MarketEvent::Book(BookQuote::new("AAPL", bid, 300, ask, 500, 1)),
+            MarketEvent::DepthSnapshot(snapshot),
+            MarketEvent::Depth(update),
+            MarketEvent::Status(MarketStatus::ticker("AAPL", TradingPhase::Halted, 1)),
+            MarketEvent::Action(CorporateAction::split("AAPL", 4, 1, 2)),
+        ];
+        for (sequence, event) in (1..).zip(events) {
+            let envelope = Envelope::new(sequence, Message::Event(event.clone()));
+            let json = serde_json::to_vec(&envelope).unwrap();
+            socket.send_to(&json, address).unwrap();
+            assert_eq!(stock_rx.recv().unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut sequence = Sequence::default();
+        assert_eq!(sequence.receive(5), 0);
+        assert_eq!(sequence.receive(6), 0);
+        assert_eq!(sequence.receive(9), 2);
+        // Reordered 7 and 8 neither lose messages nor move back
+        assert_eq!(sequence.receive(7), 0);
+        assert_eq!(sequence.receive(8), 0);
+        assert_eq!(sequence.receive(10), 0);
+        // A restarted server numbers from 1
+        assert_eq!(sequence.receive(1), 0);
+        assert_eq!(sequence.receive(2), 0);
+        let mut sequence = Sequence::default();
+        assert_eq!(sequence.receive(5000), 0);
+        assert_eq!(sequence.receive(3), 0);
+        assert_eq!(sequence.receive(5), 1);
     }
 }
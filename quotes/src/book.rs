@@ -0,0 +1,88 @@
+use crate::Price;
+use serde::{Deserialize, Serialize};
+
+/// Top of the book of a ticker: the best bid and ask with their sizes
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BookQuote {
+    /// The stock name
+    pub ticker: String,
+    /// Best price buyers pay
+    pub bid: Price,
+    /// Shares wanted at the bid
+    pub bid_size: u64,
+    /// Best price sellers accept
+    pub ask: Price,
+    /// Shares offered at the ask
+    pub ask_size: u64,
+    /// Timestamp, then the quote was published
+    pub timestamp: u64,
+}
+
+impl BookQuote {
+    /// Creates a quote of `bid_size` shares at `bid` and `ask_size` shares at `ask`
+    /// # Example
+    /// ```rust
+    /// use quotes::{BookQuote, Price};
+    /// let (bid, ask) = (Price::from_cents(18000), Price::from_cents(18002));
+    /// let book = BookQuote::new("AAPL", bid, 300, ask, 500, 1700000000000);
+    /// assert_eq!(book.spread(), Price::from_cents(2));
+    /// ```
+    pub fn new(
+        ticker: &str,
+        bid: Price,
+        bid_size: u64,
+        ask: Price,
+        ask_size: u64,
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            bid,
+            bid_size,
+            ask,
+            ask_size,
+            timestamp,
+        }
+    }
+
+    /// Difference of the ask and the bid, zero for a crossed book
+    pub fn spread(&self) -> Price {
+        self.ask.checked_sub(self.bid).unwrap_or(Price::ZERO)
+    }
+
+    /// Midpoint of the bid and the ask, rounded down to `Price` units
+    /// # Example
+    /// ```rust
+    /// use quotes::{BookQuote, Price};
+    /// let (bid, ask) = (Price::from_cents(18000), Price::from_cents(18001));
+    /// let book = BookQuote::new("AAPL", bid, 300, ask, 500, 1);
+    /// assert_eq!(book.mid().to_string(), "180.005");
+    /// ```
+    pub fn mid(&self) -> Price {
+        (self.bid + self.ask) / 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_quote_json() {
+        let book = BookQuote::new(
+            "AAPL",
+            Price::from_cents(18000),
+            300,
+            Price::from_cents(18005),
+            500,
+            42,
+        );
+        let json = serde_json::to_string(&book).unwrap();
+        assert_eq!(
+            json,
+            r#"{"ticker":"AAPL","bid":18000,"bid_size":300,"ask":18005,"ask_size":500,"timestamp":42}"#
+        );
+        assert_eq!(serde_json::from_str::<BookQuote>(&json).unwrap(), book);
+        assert!(serde_json::from_str::<crate::StockQuote>(&json).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// What happened to the ticker
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CorporateActionKind {
+    /// Every `old_shares` shares became `new_shares`, a reverse split has fewer new shares
+    Split {
+        /// Shares after the split
+        new_shares: u32,
+        /// Shares before the split
+        old_shares: u32,
+    },
+    /// The ticker trades as `symbol` from now on
+    Rename {
+        /// The new ticker
+        symbol: String,
+    },
+}
+
+impl fmt::Display for CorporateActionKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CorporateActionKind::Split {
+                new_shares,
+                old_shares,
+            } => write!(f, "split {}:{}", new_shares, old_shares),
+            CorporateActionKind::Rename { symbol } => write!(f, "rename {}", symbol),
+        }
+    }
+}
+
+/// Corporate action of one ticker, published before its first quote affected by it
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CorporateAction {
+    /// The stock name, the old one for a rename
+    pub ticker: String,
+    /// The action and its terms
+    #[serde(flatten)]
+    pub kind: CorporateActionKind,
+    /// Timestamp, then the action took effect
+    pub timestamp: u64,
+}
+
+impl CorporateAction {
+    /// Creates a split of `old_shares` into `new_shares`
+    /// # Example
+    /// ```rust
+    /// use quotes::CorporateAction;
+    /// let split = CorporateAction::split("AAPL", 4, 1, 1700000000000);
+    /// assert_eq!(split.price_factor(), 0.25);
+    /// ```
+    pub fn split(ticker: &str, new_shares: u32, old_shares: u32, timestamp: u64) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            kind: CorporateActionKind::Split {
+                new_shares,
+                old_shares,
+            },
+            timestamp,
+        }
+    }
+
+    /// Creates a rename of `ticker` to `symbol`
+    /// # Example
+    /// ```rust
+    /// use quotes::CorporateAction;
+    /// let rename = CorporateAction::rename("FB", "META", 1700000000000);
+    /// assert_eq!(rename.price_factor(), 1.0);
+    /// ```
+    pub fn rename(ticker: &str, symbol: &str, timestamp: u64) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            kind: CorporateActionKind::Rename {
+                symbol: symbol.to_string(),
+            },
+            timestamp,
+        }
+    }
+
+    /// Multiplier turning prices before the action into comparable prices after it
+    pub fn price_factor(&self) -> f64 {
+        match self.kind {
+            CorporateActionKind::Split {
+                new_shares,
+                old_shares,
+            } => old_shares as f64 / new_shares as f64,
+            CorporateActionKind::Rename { .. } => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corporate_action_json() {
+        let split = CorporateAction::split("AAPL", 1, 10, 42);
+        let json = serde_json::to_string(&split).unwrap();
+        assert_eq!(
+            json,
+            r#"{"ticker":"AAPL","action":"split","new_shares":1,"old_shares":10,"timestamp":42}"#
+        );
+        assert_eq!(split.price_factor(), 10.0);
+        let rename: CorporateAction = serde_json::from_str(
+            r#"{"ticker":"FB","action":"rename","symbol":"META","timestamp":1}"#,
+        )
+        .unwrap();
+        assert_eq!(rename, CorporateAction::rename("FB", "META", 1));
+    }
+}
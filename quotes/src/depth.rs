@@ -0,0 +1,454 @@
+use crate::Price;
+use crate::envelope::RESTART_DROP;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Formatter;
+
+/// Side of the order book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    /// Buy orders
+    Bid,
+    /// Sell orders
+    Ask,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Bid => write!(f, "bid"),
+            Side::Ask => write!(f, "ask"),
+        }
+    }
+}
+
+/// Shares resting at one price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Level {
+    /// Price of the level
+    pub price: Price,
+    /// Shares at the price
+    pub size: u64,
+}
+
+/// What happened to a price level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LevelAction {
+    /// A new price level
+    Add,
+    /// New size of an existing level
+    Modify,
+    /// The level is gone
+    Delete,
+}
+
+/// Change of one price level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LevelChange {
+    /// Side of the level
+    pub side: Side,
+    /// What happened to the level
+    pub action: LevelAction,
+    /// Price of the level
+    pub price: Price,
+    /// Shares at the price after the change, zero for a deletion
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// Incremental depth update, applied on top of the book with the previous `sequence`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DepthUpdate {
+    /// The stock name
+    pub ticker: String,
+    /// Number of the update, one more than the previous update of the ticker
+    pub sequence: u64,
+    /// Changed levels
+    pub changes: Vec<LevelChange>,
+    /// Timestamp, then the update was published
+    pub timestamp: u64,
+}
+
+/// Full depth of a ticker as of the update `sequence`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DepthSnapshot {
+    /// The stock name
+    pub ticker: String,
+    /// Number of the last update included
+    pub sequence: u64,
+    /// Bid levels, the best first
+    pub bids: Vec<Level>,
+    /// Ask levels, the best first
+    pub asks: Vec<Level>,
+    /// Timestamp, then the snapshot was taken
+    pub timestamp: u64,
+}
+
+/// Multi-level order book of one ticker rebuilt from depth snapshots and updates.
+///
+/// The book is out of sync until the first snapshot. Updates must follow each other
+/// without gaps: a missed update or an inconsistent change puts the book out of sync
+/// until the next snapshot.
+/// # Example
+/// ```rust
+/// use quotes::{DepthSnapshot, DepthUpdate, Level, OrderBook, Price};
+/// use quotes::{LevelAction, LevelChange, Side};
+/// let mut book = OrderBook::new("AAPL");
+/// let level = |cents, size| Level { price: Price::from_cents(cents), size };
+/// let snapshot = DepthSnapshot {
+///     ticker: "AAPL".to_string(),
+///     sequence: 7,
+///     bids: vec![level(18000, 300)],
+///     asks: vec![level(18002, 500)],
+///     timestamp: 1700000000000,
+/// };
+/// book.apply_snapshot(&snapshot).unwrap();
+/// let change = LevelChange {
+///     side: Side::Bid,
+///     action: LevelAction::Add,
+///     price: Price::from_cents(18001),
+///     size: 100,
+/// };
+/// let update = DepthUpdate {
+///     ticker: "AAPL".to_string(),
+///     sequence: 8,
+///     changes: vec![change],
+///     timestamp: 1700000000001,
+/// };
+/// assert_eq!(book.apply(&update), Ok(true));
+/// assert_eq!(book.best_bid(), Some(level(18001, 100)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBook {
+    ticker: String,
+    /// Last applied update, `None` while out of sync
+    sequence: Option<u64>,
+    bids: BTreeMap<Reverse<Price>, u64>,
+    asks: BTreeMap<Price, u64>,
+}
+
+impl OrderBook {
+    /// Creates an empty book waiting for a snapshot
+    pub fn new(ticker: &str) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            sequence: None,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        }
+    }
+
+    /// The stock name
+    pub fn ticker(&self) -> &str {
+        &self.ticker
+    }
+
+    /// Last applied update, `None` while the book waits for a snapshot
+    pub fn sequence(&self) -> Option<u64> {
+        self.sequence
+    }
+
+    /// Whether the book follows the published depth
+    pub fn is_synced(&self) -> bool {
+        self.sequence.is_some()
+    }
+
+    /// Bid levels, the best first
+    pub fn bids(&self) -> impl Iterator<Item = Level> + '_ {
+        self.bids.iter().map(|(Reverse(price), size)| Level {
+            price: *price,
+            size: *size,
+        })
+    }
+
+    /// Ask levels, the best first
+    pub fn asks(&self) -> impl Iterator<Item = Level> + '_ {
+        self.asks.iter().map(|(price, size)| Level {
+            price: *price,
+            size: *size,
+        })
+    }
+
+    /// The highest bid
+    pub fn best_bid(&self) -> Option<Level> {
+        self.bids().next()
+    }
+
+    /// The lowest ask
+    pub fn best_ask(&self) -> Option<Level> {
+        self.asks().next()
+    }
+
+    /// Replaces the book with `snapshot`, returns `false` for a snapshot of the update the
+    /// book is at and for a reordered older one. A sequence of 1 or one [`RESTART_DROP`]
+    /// below the book means the server numbers updates anew after a restart, the book is
+    /// reset to it
+    pub fn apply_snapshot(&mut self, snapshot: &DepthSnapshot) -> Result<bool, String> {
+        if snapshot.ticker != self.ticker {
+            return Err(format!(
+                "snapshot of {} applied to the book of {}",
+                snapshot.ticker, self.ticker
+            ));
+        }
+        if let Some(sequence) = self.sequence
+            && snapshot.sequence <= sequence
+            && snapshot.sequence != 1
+            && sequence - snapshot.sequence < RESTART_DROP
+        {
+            return Ok(false);
+        }
+        self.bids = snapshot
+            .bids
+            .iter()
+            .map(|level| (Reverse(level.price), level.size))
+            .collect();
+        self.asks = snapshot
+            .asks
+            .iter()
+            .map(|level| (level.price, level.size))
+            .collect();
+        self.sequence = Some(snapshot.sequence);
+        Ok(true)
+    }
+
+    /// Applies the next update. Returns `false` for updates received before the first
+    /// snapshot and for ones already applied, an error for a sequence gap or a change
+    /// that does not match the book, which then waits for the next snapshot
+    pub fn apply(&mut self, update: &DepthUpdate) -> Result<bool, String> {
+        if update.ticker != self.ticker {
+            return Err(format!(
+                "update of {} applied to the book of {}",
+                update.ticker, self.ticker
+            ));
+        }
+        let Some(sequence) = self.sequence else {
+            return Ok(false);
+        };
+        if update.sequence <= sequence {
+            return Ok(false);
+        }
+        if update.sequence != sequence + 1 {
+            self.sequence = None;
+            return Err(format!(
+                "{} depth gap: expected update {}, received {}",
+                self.ticker,
+                sequence + 1,
+                update.sequence
+            ));
+        }
+        for change in &update.changes {
+            if let Err(e) = self.apply_change(change) {
+                self.sequence = None;
+                return Err(format!(
+                    "{} depth update {}: {}",
+                    self.ticker, update.sequence, e
+                ));
+            }
+        }
+        self.sequence = Some(update.sequence);
+        Ok(true)
+    }
+
+    fn apply_change(&mut self, change: &LevelChange) -> Result<(), String> {
+        let size = match change.side {
+            Side::Bid => self.bids.get_mut(&Reverse(change.price)),
+            Side::Ask => self.asks.get_mut(&change.price),
+        };
+        match (change.action, size) {
+            (LevelAction::Add, None) => {
+                match change.side {
+                    Side::Bid => self.bids.insert(Reverse(change.price), change.size),
+                    Side::Ask => self.asks.insert(change.price, change.size),
+                };
+            }
+            (LevelAction::Modify, Some(size)) => *size = change.size,
+            (LevelAction::Delete, Some(_)) => {
+                match change.side {
+                    Side::Bid => self.bids.remove(&Reverse(change.price)),
+                    Side::Ask => self.asks.remove(&change.price),
+                };
+            }
+            (LevelAction::Add, Some(_)) => {
+                return Err(format!("{} {} already exists", change.side, change.price));
+            }
+            (_, None) => return Err(format!("no {} at {}", change.side, change.price)),
+        }
+        Ok(())
+    }
+
+    /// Changes turning this book into one with `bids` and `asks`, given the best first
+    pub fn changes_to(&self, bids: &[Level], asks: &[Level]) -> Vec<LevelChange> {
+        let mut changes = Vec::new();
+        for (side, current, target) in [
+            (Side::Bid, self.bids().collect::<Vec<Level>>(), bids),
+            (Side::Ask, self.asks().collect(), asks),
+        ] {
+            for level in &current {
+                if !target.iter().any(|t| t.price == level.price) {
+                    changes.push(LevelChange {
+                        side,
+                        action: LevelAction::Delete,
+                        price: level.price,
+                        size: 0,
+                    });
+                }
+            }
+            for level in target {
+                let action = match current.iter().find(|c| c.price == level.price) {
+                    None => LevelAction::Add,
+                    Some(c) if c.size != level.size => LevelAction::Modify,
+                    Some(_) => continue,
+                };
+                changes.push(LevelChange {
+                    side,
+                    action,
+                    price: level.price,
+                    size: level.size,
+                });
+            }
+        }
+        changes
+    }
+
+    /// Full depth as of the last applied update
+    pub fn snapshot(&self, timestamp: u64) -> DepthSnapshot {
+        DepthSnapshot {
+            ticker: self.ticker.clone(),
+            sequence: self.sequence.unwrap_or_default(),
+            bids: self.bids().collect(),
+            asks: self.asks().collect(),
+            timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(cents: u64, size: u64) -> Level {
+        Level {
+            price: Price::from_cents(cents),
+            size,
+        }
+    }
+
+    fn snapshot(sequence: u64) -> DepthSnapshot {
+        DepthSnapshot {
+            ticker: "AAPL".to_string(),
+            sequence,
+            bids: vec![level(100, 10), level(99, 20)],
+            asks: vec![level(101, 30), level(102, 40)],
+            timestamp: 1,
+        }
+    }
+
+    fn update(sequence: u64, changes: Vec<LevelChange>) -> DepthUpdate {
+        DepthUpdate {
+            ticker: "AAPL".to_string(),
+            sequence,
+            changes,
+            timestamp: 2,
+        }
+    }
+
+    #[test]
+    fn test_apply_updates() {
+        let mut book = OrderBook::new("AAPL");
+        let target = [level(100, 15), level(98, 5)];
+        let changes = OrderBook::new("AAPL").changes_to(&target, &[]);
+        assert!(!book.apply(&update(5, changes)).unwrap());
+        assert!(book.apply_snapshot(&snapshot(4)).unwrap());
+
+        let changes = book.changes_to(&target, &[level(101, 30)]);
+        let actions: Vec<(Side, LevelAction)> =
+            changes.iter().map(|c| (c.side, c.action)).collect();
+        assert_eq!(
+            actions,
+            vec![
+                (Side::Bid, LevelAction::Delete),
+                (Side::Bid, LevelAction::Modify),
+                (Side::Bid, LevelAction::Add),
+                (Side::Ask, LevelAction::Delete),
+            ]
+        );
+        let next = update(5, changes);
+        assert!(book.apply(&next).unwrap());
+        assert!(!book.apply(&next).unwrap());
+        assert_eq!(book.bids().collect::<Vec<Level>>(), target);
+        assert_eq!(book.best_ask(), Some(level(101, 30)));
+        assert_eq!(book.snapshot(3).sequence, 5);
+        assert!(!book.apply_snapshot(&snapshot(5)).unwrap());
+    }
+
+    #[test]
+    fn test_gap_waits_for_snapshot() {
+        let mut book = OrderBook::new("AAPL");
+        book.apply_snapshot(&snapshot(1)).unwrap();
+        assert!(book.apply(&update(3, vec![])).is_err());
+        assert!(!book.is_synced());
+        assert!(!book.apply(&update(4, vec![])).unwrap());
+        book.apply_snapshot(&snapshot(4)).unwrap();
+        assert!(book.apply(&update(5, vec![])).unwrap());
+
+        let missing = LevelChange {
+            side: Side::Ask,
+            action: LevelAction::Modify,
+            price: Price::from_cents(105),
+            size: 1,
+        };
+        assert!(book.apply(&update(6, vec![missing])).is_err());
+        assert_eq!(book.sequence(), None);
+    }
+
+    #[test]
+    fn test_snapshot_after_restart() {
+        let mut book = OrderBook::new("AAPL");
+        book.apply_snapshot(&snapshot(1040)).unwrap();
+        assert!(book.apply(&update(1041, vec![])).unwrap());
+        let mut restarted = snapshot(2);
+        restarted.bids = vec![level(90, 10)];
+        assert!(book.apply_snapshot(&restarted).unwrap());
+        assert_eq!(book.sequence(), Some(2));
+        assert_eq!(book.best_bid(), Some(level(90, 10)));
+        assert!(book.apply(&update(3, vec![])).unwrap());
+        assert!(book.apply_snapshot(&snapshot(1)).unwrap());
+        assert_eq!(book.sequence(), Some(1));
+    }
+
+    #[test]
+    fn test_reordered_snapshots() {
+        let mut book = OrderBook::new("AAPL");
+        let mut newer = snapshot(40);
+        newer.bids = vec![level(90, 10)];
+        assert!(book.apply_snapshot(&newer).unwrap());
+        assert!(!book.apply_snapshot(&snapshot(30)).unwrap());
+        assert_eq!(book.sequence(), Some(40));
+        assert_eq!(book.best_bid(), Some(level(90, 10)));
+        assert!(book.apply(&update(41, vec![])).unwrap());
+    }
+
+    #[test]
+    fn test_depth_json() {
+        let change = LevelChange {
+            side: Side::Bid,
+            action: LevelAction::Delete,
+            price: Price::from_cents(100),
+            size: 0,
+        };
+        let json = serde_json::to_string(&update(2, vec![change])).unwrap();
+        assert_eq!(
+            json,
+            r#"{"ticker":"AAPL","sequence":2,"changes":[{"side":"bid","action":"delete","price":100,"size":0}],"timestamp":2}"#
+        );
+        let json = serde_json::to_string(&snapshot(1)).unwrap();
+        assert_eq!(
+            serde_json::from_str::<DepthSnapshot>(&json).unwrap(),
+            snapshot(1)
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::MarketEvent;
+use serde::{Deserialize, Serialize};
+
+/// Version of the UDP protocol spoken by this crate
+pub const PROTOCOL_VERSION: u16 = 1;
+/// Keepalive of clients older than the envelope, deprecated. Servers still answer it
+/// with [`LEGACY_PONG`] until such clients are gone
+pub const LEGACY_PING: &[u8] = b"PING";
+/// Answer to [`LEGACY_PING`], deprecated
+pub const LEGACY_PONG: &[u8] = b"PONG";
+/// A sequence number this far below the last one, or a new first number, means the server
+/// restarted and numbers its messages and depth updates anew
+pub const RESTART_DROP: u64 = 1000;
+
+/// What an envelope carries, tagged with its kind in the `type` field
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum Message {
+    /// Keepalive of the subscriber
+    Ping,
+    /// Answer to a ping, numbered as the ping
+    Pong,
+    /// Market data, its kind is one of [`MarketEvent`]
+    #[serde(untagged)]
+    Event(MarketEvent),
+}
+
+/// Versioned, numbered UDP datagram:
+/// `{"version":1,"type":"quote","sequence":42,"payload":{...}}`
+/// # Example
+/// ```rust
+/// use quotes::{Envelope, MarketEvent, Message, Price, StockQuote};
+/// let quote = StockQuote::new("AAPL", Price::from_cents(18000), 1000);
+/// let envelope = Envelope::new(42, Message::Event(MarketEvent::Quote(quote)));
+/// let json = serde_json::to_vec(&envelope).unwrap();
+/// assert_eq!(Envelope::parse(&json), Ok(envelope));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Envelope {
+    /// Protocol version of the sender
+    pub version: u16,
+    /// Number of the message in its stream, one more than the previous one
+    pub sequence: u64,
+    /// The message with its `type` and `payload`
+    #[serde(flatten)]
+    pub message: Message,
+}
+
+/// Every message type of this protocol version
+const MESSAGE_TYPES: [&str; 9] = [
+    "ping",
+    "pong",
+    "quote",
+    "book",
+    "trade",
+    "depth",
+    "depth_snapshot",
+    "status",
+    "action",
+];
+
+/// Fields every version of the envelope keeps
+#[derive(Deserialize)]
+struct Header {
+    version: u16,
+    #[serde(rename = "type")]
+    message_type: String,
+}
+
+/// Envelope of market data, its errors name the field at fault
+#[derive(Deserialize)]
+struct EventEnvelope {
+    #[serde(rename = "sequence")]
+    _sequence: u64,
+    #[serde(flatten)]
+    _event: MarketEvent,
+}
+
+impl Envelope {
+    /// Envelope of the current protocol version
+    pub fn new(sequence: u64, message: Message) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            sequence,
+            message,
+        }
+    }
+
+    /// Reads a datagram. Newer protocol versions and message types unknown to this
+    /// version are reported as errors naming them, instead of being misread. A malformed
+    /// message of a known type reports what is wrong with it
+    pub fn parse(data: &[u8]) -> Result<Envelope, String> {
+        let header: Header =
+            serde_json::from_slice(data).map_err(|e| format!("invalid envelope: {}", e))?;
+        if header.version != PROTOCOL_VERSION {
+            return Err(format!(
+                "unsupported protocol version {}, expected {}",
+                header.version, PROTOCOL_VERSION
+            ));
+        }
+        if !MESSAGE_TYPES.contains(&header.message_type.as_str()) {
+            return Err(format!(
+                "unsupported message type '{}'",
+                header.message_type
+            ));
+        }
+        serde_json::from_slice(data).map_err(|e| {
+            let e = serde_json::from_slice::<EventEnvelope>(data)
+                .err()
+                .unwrap_or(e);
+            format!("invalid {} message: {}", header.message_type, e)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BookQuote, CorporateAction, DepthSnapshot, DepthUpdate, MarketStatus, Price, Side,
+        StockQuote, Trade, TradingPhase,
+    };
+
+    #[test]
+    fn test_envelope_json() {
+        let ping = Envelope::new(1, Message::Ping);
+        let json = serde_json::to_string(&ping).unwrap();
+        assert_eq!(json, r#"{"version":1,"sequence":1,"type":"ping"}"#);
+        assert_eq!(Envelope::parse(json.as_bytes()), Ok(ping));
+
+        let trade = Trade::new("AAPL", 7, Price::from_cents(18000), 300, Side::Ask, 42);
+        let envelope = Envelope::new(2, Message::Event(MarketEvent::Trade(trade)));
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert!(json.starts_with(r#"{"version":1,"sequence":2,"type":"trade","payload":{"#));
+        assert_eq!(Envelope::parse(json.as_bytes()), Ok(envelope));
+
+        let rename = CorporateAction::rename("FB", "META", 1);
+        let envelope = Envelope::new(3, Message::Event(MarketEvent::Action(rename)));
+        let json = serde_json::to_vec(&envelope).unwrap();
+        assert_eq!(Envelope::parse(&json), Ok(envelope));
+    }
+
+    #[test]
+    fn test_message_types() {
+        let price = Price::from_cents(18000);
+        let events = [
+            MarketEvent::Quote(StockQuote::new("AAPL", price, 1000)),
+            MarketEvent::Book(BookQuote::new("AAPL", price, 100, price, 200, 1)),
+            MarketEvent::Trade(Trade::new("AAPL", 1, price, 50, Side::Bid, 1)),
+            MarketEvent::Depth(DepthUpdate {
+                ticker: "AAPL".to_string(),
+                sequence: 1,
+                changes: vec![],
+                timestamp: 1,
+            }),
+            MarketEvent::DepthSnapshot(DepthSnapshot {
+                ticker: "AAPL".to_string(),
+                sequence: 1,
+                bids: vec![],
+                asks: vec![],
+                timestamp: 1,
+            }),
+            MarketEvent::Status(MarketStatus::market(TradingPhase::Open, 1)),
+            MarketEvent::Action(CorporateAction::rename("FB", "META", 1)),
+        ];
+        let messages = [Message::Ping, Message::Pong]
+            .into_iter()
+            .chain(events.into_iter().map(Message::Event));
+        for (message, name) in messages.zip(MESSAGE_TYPES) {
+            let json = serde_json::to_vec(&Envelope::new(1, message)).unwrap();
+            let header: Header = serde_json::from_slice(&json).unwrap();
+            assert_eq!(header.message_type, name);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let newer = br#"{"version":2,"sequence":1,"type":"quote","payload":{}}"#;
+        assert_eq!(
+            Envelope::parse(newer),
+            Err("unsupported protocol version 2, expected 1".to_string())
+        );
+        let unknown = br#"{"version":1,"sequence":1,"type":"news","payload":{"text":"hi"}}"#;
+        assert_eq!(
+            Envelope::parse(unknown),
+            Err("unsupported message type 'news'".to_string())
+        );
+        let broken = br#"{"version":1,"sequence":1,"type":"quote","payload":{"ticker":"AAPL"}}"#;
+        let error = Envelope::parse(broken).unwrap_err();
+        assert!(error.starts_with("invalid quote message: "), "{}", error);
+        assert!(error.contains("price"), "{}", error);
+        assert!(Envelope::parse(b"PONG").is_err());
+        let legacy = br#"{"ticker":"AAPL","price":18000,"volume":10,"timestamp":42}"#;
+        assert!(Envelope::parse(legacy).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    BookQuote, CorporateAction, DepthSnapshot, DepthUpdate, MarketStatus, StockQuote, Trade,
+};
+use serde::{Deserialize, Serialize};
+
+/// Any market data message of the stream, tagged with its kind in the `type` field
+/// and carrying the message itself in `payload`
+/// # Example
+/// ```rust
+/// use quotes::{MarketEvent, Price, StockQuote};
+/// let quote = StockQuote::new("AAPL", Price::from_cents(18000), 1000);
+/// let json = serde_json::to_string(&MarketEvent::Quote(quote)).unwrap();
+/// assert!(json.starts_with(r#"{"type":"quote","payload":{"ticker":"AAPL""#));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum MarketEvent {
+    /// Last trade price and volume
+    Quote(StockQuote),
+    /// Top of the book
+    Book(BookQuote),
+    /// Single trade print
+    Trade(Trade),
+    /// Incremental depth update
+    Depth(DepthUpdate),
+    /// Full depth
+    DepthSnapshot(DepthSnapshot),
+    /// Session or halt state change
+    Status(MarketStatus),
+    /// Corporate action
+    Action(CorporateAction),
+}
+
+impl MarketEvent {
+    /// The ticker of the message, `None` for a status of the whole market
+    pub fn ticker(&self) -> Option<&str> {
+        match self {
+            MarketEvent::Quote(quote) => Some(&quote.ticker),
+            MarketEvent::Book(book) => Some(&book.ticker),
+            MarketEvent::Trade(trade) => Some(&trade.ticker),
+            MarketEvent::Depth(update) => Some(&update.ticker),
+            MarketEvent::DepthSnapshot(snapshot) => Some(&snapshot.ticker),
+            MarketEvent::Status(status) => status.ticker.as_deref(),
+            MarketEvent::Action(action) => Some(&action.ticker),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Price, Side, TradingPhase};
+
+    #[test]
+    fn test_event_round_trip() {
+        let price = Price::from_cents(18000);
+        let events = [
+            MarketEvent::Quote(StockQuote::new("AAPL", price, 1000)),
+            MarketEvent::Book(BookQuote::new("AAPL", price, 100, price, 200, 1)),
+            MarketEvent::Trade(Trade::new("AAPL", 1, price, 50, Side::Bid, 1)),
+            MarketEvent::Status(MarketStatus::market(TradingPhase::Open, 1)),
+            MarketEvent::Action(CorporateAction::rename("FB", "META", 1)),
+        ];
+        for event in events {
+            let json = serde_json::to_string(&event).unwrap();
+            assert_eq!(serde_json::from_str::<MarketEvent>(&json).unwrap(), event);
+        }
+
+        let action = r#"{"type":"action","payload":{"ticker":"AAPL","action":"split","new_shares":4,"old_shares":1,"timestamp":1}}"#;
+        let event: MarketEvent = serde_json::from_str(action).unwrap();
+        assert_eq!(event.ticker(), Some("AAPL"));
+        assert!(serde_json::from_str::<MarketEvent>(r#"{"ticker":"AAPL"}"#).is_err());
+    }
+}
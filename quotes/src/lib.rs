@@ -5,13 +5,40 @@
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+mod book;
+mod corporate;
+mod depth;
+mod envelope;
+mod event;
+mod price;
+mod source;
+mod status;
+mod stream;
+mod trade;
+mod universe;
+
+pub use book::BookQuote;
+pub use corporate::{CorporateAction, CorporateActionKind};
+pub use depth::{DepthSnapshot, DepthUpdate, Level, LevelAction, LevelChange, OrderBook, Side};
+pub use envelope::{Envelope, LEGACY_PING, LEGACY_PONG, Message, PROTOCOL_VERSION, RESTART_DROP};
+pub use event::MarketEvent;
+pub use price::Price;
+pub use source::{Clock, FixedClock, QuoteSource, SourceEvent, SystemClock};
+pub use status::{MarketStatus, TradingPhase};
+pub use stream::StreamKind;
+pub use trade::{BLOCK_SIZE, ROUND_LOT, Trade, TradeCondition};
+pub use universe::{
+    Bounds, MAX_PRICE, MAX_VOLUME, SectorCorrelation, TickerConfig, Universe, UniverseFormat,
+    parse_universe,
+};
+
 /// Main data of the stock
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct StockQuote {
     /// The stock name
     pub ticker: String,
     /// Current price
-    pub price: u64,
+    pub price: Price,
     /// Volume of stocks traded
     pub volume: u64,
     /// Timestamp, then stock change was published
@@ -22,10 +49,10 @@ impl StockQuote {
     /// Creates new `StockQuote` instance with current timestamp
     /// # Example:
     /// ```rust
-    /// use quotes::StockQuote;
-    /// let stock = StockQuote::new("AAPL", 180, 3000000);
+    /// use quotes::{Price, StockQuote};
+    /// let stock = StockQuote::new("AAPL", Price::from_cents(18000), 3000000);
     /// ```
-    pub fn new(ticker: &str, price: u64, volume: u64) -> Self {
+    pub fn new(ticker: &str, price: Price, volume: u64) -> Self {
         Self {
             ticker: ticker.to_string(),
             price,
@@ -40,17 +67,29 @@ impl StockQuote {
     /// Update price, volume and current timestamp
     /// # Example
     /// ```rust
-    /// use quotes::StockQuote;
-    /// let mut stock = StockQuote::new("AAPL", 180, 3000000);
-    /// stock.update(200, 3500000);
+    /// use quotes::{Price, StockQuote};
+    /// let mut stock = StockQuote::new("AAPL", Price::from_cents(18000), 3000000);
+    /// stock.update("200.00".parse().unwrap(), 3500000);
     /// ```
-    pub fn update(&mut self, price: u64, volume: u64) {
-        self.price = price;
-        self.volume = volume;
-        self.timestamp = SystemTime::now()
+    pub fn update(&mut self, price: Price, volume: u64) {
+        let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_millis() as u64;
+        self.update_at(price, volume, timestamp);
+    }
+
+    /// Update price, volume and set the given timestamp
+    /// # Example
+    /// ```rust
+    /// use quotes::{Price, StockQuote};
+    /// let mut stock = StockQuote::new("AAPL", Price::from_cents(18000), 3000000);
+    /// stock.update_at(Price::from_cents(20000), 3500000, 1700000000000);
+    /// ```
+    pub fn update_at(&mut self, price: Price, volume: u64, timestamp: u64) {
+        self.price = price;
+        self.volume = volume;
+        self.timestamp = timestamp;
     }
 }
 
@@ -83,9 +122,40 @@ mod tests {
 
     #[test]
     fn test_update_stock() {
-        let mut stock = StockQuote::new("AAPL", 200, 3000000);
-        stock.update(210, 3300000);
-        assert_eq!(stock.price, 210);
+        let mut stock = StockQuote::new("AAPL", Price::from_cents(200), 3000000);
+        stock.update(Price::from_cents(210), 3300000);
+        assert_eq!(stock.price, Price::from_cents(210));
         assert_eq!(stock.volume, 3300000);
     }
+
+    #[test]
+    fn test_update_stock_at() {
+        let mut stock = StockQuote::new("AAPL", Price::from_cents(200), 3000000);
+        stock.update_at(Price::from_cents(210), 3300000, 42);
+        assert_eq!(stock.price, Price::from_cents(210));
+        assert_eq!(stock.timestamp, 42);
+    }
+
+    #[test]
+    fn test_legacy_quote_json() {
+        let legacy = r#"{"ticker":"AAPL","price":18025,"volume":10,"timestamp":42}"#;
+        let mut stock: StockQuote = serde_json::from_str(legacy).unwrap();
+        assert_eq!(stock.price.to_string(), "180.25");
+        assert_eq!(serde_json::to_string(&stock).unwrap(), legacy);
+
+        /// Quote as consumers before `Price` read it
+        #[derive(Deserialize)]
+        struct LegacyQuote {
+            price: u64,
+        }
+        let old: LegacyQuote =
+            serde_json::from_str(&serde_json::to_string(&stock).unwrap()).unwrap();
+        assert_eq!(old.price, 18025);
+
+        stock.price = "180.2575".parse().unwrap();
+        assert_eq!(
+            serde_json::to_string(&stock).unwrap(),
+            r#"{"ticker":"AAPL","price":"180.2575","volume":10,"timestamp":42}"#
+        );
+    }
 }
@@ -0,0 +1,304 @@
+use serde::de::{Error, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::fmt::Formatter;
+use std::iter::Sum;
+use std::ops::{Add, Div, Mul, Sub};
+use std::str::FromStr;
+
+/// `10^Price::SCALE`
+const UNITS_PER_WHOLE: u64 = 10_u64.pow(Price::SCALE);
+/// Units in one hundredth, the scale of integer prices before `Price`
+const UNITS_PER_CENT: u64 = UNITS_PER_WHOLE / 100;
+/// Fraction digits always shown by `Display`
+const MIN_DISPLAYED_DIGITS: usize = 2;
+
+/// Fixed-point price with `Price::SCALE` decimal places.
+///
+/// Serialized as the integer number of hundredths published before, e.g. `18025` for
+/// `180.25`, so existing consumers keep working. Only a price with a fraction of a cent,
+/// which the integer format cannot carry, is written as a decimal string such as
+/// `"180.2575"`. Both forms are read back. Arithmetic saturates instead of overflowing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Price(u64);
+
+impl Price {
+    /// Decimal places of every price, the smallest possible tick is `10^-SCALE`
+    pub const SCALE: u32 = 4;
+    /// Zero price
+    pub const ZERO: Price = Price(0);
+
+    /// Creates a price from units of `10^-SCALE`
+    /// # Example
+    /// ```rust
+    /// use quotes::Price;
+    /// assert_eq!(Price::from_units(1_802_500).to_string(), "180.25");
+    /// ```
+    pub const fn from_units(units: u64) -> Self {
+        Self(units)
+    }
+
+    /// Creates a price from hundredths, the meaning of integer prices
+    /// # Example
+    /// ```rust
+    /// use quotes::Price;
+    /// assert_eq!(Price::from_cents(18025), "180.25".parse().unwrap());
+    /// ```
+    pub const fn from_cents(cents: u64) -> Self {
+        Self(cents.saturating_mul(UNITS_PER_CENT))
+    }
+
+    /// The price in units of `10^-SCALE`
+    pub const fn units(self) -> u64 {
+        self.0
+    }
+
+    /// Whether the price is zero
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The price as a floating point number, for display and statistics only
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / UNITS_PER_WHOLE as f64
+    }
+
+    /// The nearest multiple of `tick`, never less than one tick; a zero tick keeps the price
+    /// # Example
+    /// ```rust
+    /// use quotes::Price;
+    /// let price: Price = "180.27".parse().unwrap();
+    /// assert_eq!(price.round_to_tick(Price::from_cents(5)).to_string(), "180.25");
+    /// ```
+    pub fn round_to_tick(self, tick: Price) -> Self {
+        if tick.is_zero() {
+            return self;
+        }
+        let ticks = self.0.saturating_add(tick.0 / 2) / tick.0;
+        Self(ticks.max(1) * tick.0)
+    }
+
+    /// `self + other`, `None` on overflow
+    pub fn checked_add(self, other: Price) -> Option<Self> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    /// `self - other`, `None` if the result would be negative
+    pub fn checked_sub(self, other: Price) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    /// Parses a price of a text input: a decimal is taken as is and an integer as
+    /// hundredths, the way the integer prices of these inputs were always meant
+    /// # Example
+    /// ```rust
+    /// use quotes::Price;
+    /// assert_eq!(Price::parse_cents_or_decimal("18025"), Ok(Price::from_cents(18025)));
+    /// assert_eq!(Price::parse_cents_or_decimal("180.25"), Ok(Price::from_cents(18025)));
+    /// ```
+    pub fn parse_cents_or_decimal(s: &str) -> Result<Self, String> {
+        if s.contains('.') {
+            return s.parse();
+        }
+        let cents: u64 = s
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid price '{}', expected e.g. 18025 or 180.25", s))?;
+        cents
+            .checked_mul(UNITS_PER_CENT)
+            .map(Self)
+            .ok_or_else(|| format!("price '{}' is too large", s))
+    }
+}
+
+impl Add for Price {
+    type Output = Price;
+
+    /// Saturates at the largest price
+    fn add(self, other: Price) -> Price {
+        Price(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Price {
+    type Output = Price;
+
+    /// Saturates at zero, prices are never negative
+    fn sub(self, other: Price) -> Price {
+        Price(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Mul<u64> for Price {
+    type Output = Price;
+
+    /// Saturates at the largest price
+    fn mul(self, factor: u64) -> Price {
+        Price(self.0.saturating_mul(factor))
+    }
+}
+
+impl Div<u64> for Price {
+    type Output = Price;
+
+    /// Rounds towards zero
+    fn div(self, divisor: u64) -> Price {
+        Price(self.0 / divisor)
+    }
+}
+
+impl Sum for Price {
+    fn sum<I: Iterator<Item = Price>>(iter: I) -> Price {
+        iter.fold(Price::ZERO, Add::add)
+    }
+}
+
+impl FromStr for Price {
+    type Err = String;
+
+    /// Parses a non-negative decimal with up to `SCALE` fraction digits, e.g. `180` or `180.25`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid price '{}', expected e.g. 180.25", s);
+        let (whole, fraction) = s.trim().split_once('.').unwrap_or((s.trim(), ""));
+        let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty() || !digits(whole) || !digits(fraction) {
+            return Err(invalid());
+        }
+        if fraction.len() > Price::SCALE as usize {
+            return Err(format!(
+                "price '{}' has more than {} decimal places",
+                s,
+                Price::SCALE
+            ));
+        }
+        let whole: u64 = whole.parse().map_err(|_| invalid())?;
+        let fraction: u64 = format!("{:0<width$}", fraction, width = Price::SCALE as usize)
+            .parse()
+            .map_err(|_| invalid())?;
+        whole
+            .checked_mul(UNITS_PER_WHOLE)
+            .and_then(|units| units.checked_add(fraction))
+            .map(Price)
+            .ok_or_else(|| format!("price '{}' is too large", s))
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let fraction = format!(
+            "{:0width$}",
+            self.0 % UNITS_PER_WHOLE,
+            width = Price::SCALE as usize
+        );
+        let trimmed = fraction.trim_end_matches('0');
+        let shown = trimmed.len().max(MIN_DISPLAYED_DIGITS);
+        write!(f, "{}.{}", self.0 / UNITS_PER_WHOLE, &fraction[..shown])
+    }
+}
+
+impl Serialize for Price {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0.is_multiple_of(UNITS_PER_CENT) {
+            serializer.serialize_u64(self.0 / UNITS_PER_CENT)
+        } else {
+            serializer.serialize_str(&self.to_string())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Price {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PriceVisitor)
+    }
+}
+
+struct PriceVisitor;
+
+impl Visitor<'_> for PriceVisitor {
+    type Value = Price;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a decimal string or an integer number of hundredths")
+    }
+
+    fn visit_u64<E: Error>(self, cents: u64) -> Result<Price, E> {
+        cents
+            .checked_mul(UNITS_PER_CENT)
+            .map(Price)
+            .ok_or_else(|| E::custom(format!("price {} is too large", cents)))
+    }
+
+    fn visit_i64<E: Error>(self, cents: i64) -> Result<Price, E> {
+        let cents =
+            u64::try_from(cents).map_err(|_| E::custom(format!("price {} is negative", cents)))?;
+        self.visit_u64(cents)
+    }
+
+    fn visit_str<E: Error>(self, s: &str) -> Result<Price, E> {
+        s.parse().map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let price: Price = "180.25".parse().unwrap();
+        assert_eq!(price.units(), 1_802_500);
+        assert_eq!(price.to_string(), "180.25");
+        assert_eq!("180".parse::<Price>().unwrap().to_string(), "180.00");
+        assert_eq!("0.0001".parse::<Price>().unwrap().to_string(), "0.0001");
+        assert_eq!("12.5".parse::<Price>().unwrap(), Price::from_cents(1250));
+        for invalid in [
+            "",
+            "-1",
+            "1.2.3",
+            ".5",
+            "1.23456",
+            "1e3",
+            "99999999999999999",
+        ] {
+            assert!(invalid.parse::<Price>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let price = Price::from_cents(1000);
+        assert_eq!(price + Price::from_cents(1), Price::from_cents(1001));
+        assert_eq!(price - Price::from_cents(1), Price::from_cents(999));
+        assert_eq!(price * 3 / 2, Price::from_cents(1500));
+        assert_eq!(Price::ZERO.checked_sub(price), None);
+        assert_eq!(Price::ZERO - price, Price::ZERO);
+        assert_eq!(
+            Price::from_units(u64::MAX) + price,
+            Price::from_units(u64::MAX)
+        );
+        assert_eq!(price * u64::MAX, Price::from_units(u64::MAX));
+        let tick = Price::from_cents(25);
+        assert_eq!("10.12".parse::<Price>().unwrap().round_to_tick(tick), price);
+        assert_eq!(
+            "10.13".parse::<Price>().unwrap().round_to_tick(tick),
+            "10.25".parse().unwrap()
+        );
+        assert_eq!(Price::ZERO.round_to_tick(tick), tick);
+    }
+
+    #[test]
+    fn test_json() {
+        let price: Price = serde_json::from_str(r#""180.25""#).unwrap();
+        assert_eq!(serde_json::to_string(&price).unwrap(), "18025");
+        let legacy: Price = serde_json::from_str("18025").unwrap();
+        assert_eq!(legacy, price);
+        let fine: Price = "180.2575".parse().unwrap();
+        assert_eq!(serde_json::to_string(&fine).unwrap(), r#""180.2575""#);
+        assert_eq!(
+            serde_json::from_str::<Price>(r#""180.2575""#).unwrap(),
+            fine
+        );
+        assert!(serde_json::from_str::<Price>("-1").is_err());
+        assert!(serde_json::from_str::<Price>("180.25").is_err());
+    }
+}
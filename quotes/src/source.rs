@@ -0,0 +1,149 @@
+use crate::{
+    BookQuote, CorporateAction, DepthSnapshot, DepthUpdate, MarketStatus, StockQuote, Trade,
+};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Feed of quotes a server publishes to its subscribers
+///
+/// The server only asks the source for the next quote and does the delivery itself,
+/// so a new feed is a type implementing this trait.
+/// # Example
+/// ```rust
+/// use quotes::{Price, QuoteSource, StockQuote};
+/// use std::time::Duration;
+///
+/// struct Constant;
+///
+/// impl QuoteSource for Constant {
+///     fn next_quote(&mut self) -> Option<(Duration, StockQuote)> {
+///         let quote = StockQuote::new("AAPL", Price::from_cents(18000), 100);
+///         Some((Duration::from_millis(10), quote))
+///     }
+/// }
+///
+/// assert!(Constant.next_event().is_some());
+/// ```
+pub trait QuoteSource: Send {
+    /// Next quote and the delay before publishing it, `None` once the feed is exhausted
+    fn next_quote(&mut self) -> Option<(Duration, StockQuote)>;
+
+    /// Next event and the delay before publishing it, sources that follow a trading
+    /// session override it to publish status changes and pause outside trading hours
+    fn next_event(&mut self) -> Option<(Duration, SourceEvent)> {
+        self.next_quote()
+            .map(|(delay, quote)| (delay, SourceEvent::Quote(quote)))
+    }
+}
+
+/// What a source publishes
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceEvent {
+    /// Last trade price and volume
+    Quote(StockQuote),
+    /// Trade print, its sizes add up to the volume growth of the preceding quote
+    Trade(Trade),
+    /// Top of the book, published after the trade that moved it
+    Book(BookQuote),
+    /// Changed depth levels, published after the top of the book
+    Depth(DepthUpdate),
+    /// Full depth of a ticker, published periodically
+    DepthSnapshot(DepthSnapshot),
+    /// Session or halt state change
+    Status(MarketStatus),
+    /// Split or rename, published before the quotes it affects
+    Action(CorporateAction),
+    /// Nothing to publish, the source is asked again after the delay
+    Idle,
+}
+
+impl SourceEvent {
+    /// Stamps the event with the time it is published at
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        match self {
+            SourceEvent::Quote(quote) => quote.timestamp = timestamp,
+            SourceEvent::Trade(trade) => trade.timestamp = timestamp,
+            SourceEvent::Book(book) => book.timestamp = timestamp,
+            SourceEvent::Depth(update) => update.timestamp = timestamp,
+            SourceEvent::DepthSnapshot(snapshot) => snapshot.timestamp = timestamp,
+            SourceEvent::Status(status) => status.timestamp = timestamp,
+            SourceEvent::Action(action) => action.timestamp = timestamp,
+            SourceEvent::Idle => {}
+        }
+    }
+}
+
+/// Time source for quote timestamps
+pub trait Clock: Send {
+    /// Current time in unix milliseconds
+    fn now(&mut self) -> u64;
+}
+
+/// Wall clock time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&mut self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+/// Clock starting at a fixed time and advancing by a fixed step on every reading, for
+/// reproducible timestamps. Written `START:STEP` in milliseconds
+/// # Example
+/// ```rust
+/// use quotes::{Clock, FixedClock};
+/// let mut clock: FixedClock = "1700000000000:10".parse().unwrap();
+/// assert_eq!(clock.now(), 1700000000000);
+/// assert_eq!(clock.now(), 1700000000010);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock {
+    next: u64,
+    step: u64,
+}
+
+impl FixedClock {
+    /// Clock reading `start` first, then `step` milliseconds more on every reading
+    pub fn new(start: u64, step: u64) -> Self {
+        Self { next: start, step }
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&mut self) -> u64 {
+        let now = self.next;
+        self.next = self.next.saturating_add(self.step);
+        now
+    }
+}
+
+impl FromStr for FixedClock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid clock '{}', expected START:STEP in milliseconds", s);
+        let (start, step) = s.trim().split_once(':').ok_or_else(invalid)?;
+        let start = start.parse().map_err(|_| invalid())?;
+        let step = step.parse().map_err(|_| invalid())?;
+        Ok(Self::new(start, step))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_clock() {
+        let mut clock = FixedClock::new(100, 10);
+        assert_eq!((clock.now(), clock.now()), (100, 110));
+        assert_eq!("100:10".parse::<FixedClock>(), Ok(FixedClock::new(100, 10)));
+        assert!("100".parse::<FixedClock>().is_err());
+        assert!("100:-1".parse::<FixedClock>().is_err());
+    }
+}
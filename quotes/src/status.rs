@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// Trading phase of the market or of a single ticker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradingPhase {
+    /// Opening auction: indicative prices, no volume
+    PreOpen,
+    /// Continuous trading
+    Open,
+    /// Closing auction: indicative prices, no volume
+    ClosingAuction,
+    /// Nothing is published
+    Closed,
+    /// Trading in the ticker is suspended
+    Halted,
+}
+
+impl fmt::Display for TradingPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TradingPhase::PreOpen => write!(f, "pre_open"),
+            TradingPhase::Open => write!(f, "open"),
+            TradingPhase::ClosingAuction => write!(f, "closing_auction"),
+            TradingPhase::Closed => write!(f, "closed"),
+            TradingPhase::Halted => write!(f, "halted"),
+        }
+    }
+}
+
+/// Change of the market session, or of one ticker's state when `ticker` is set
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MarketStatus {
+    /// The stock name, `None` for the whole market
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticker: Option<String>,
+    /// Phase entered
+    pub phase: TradingPhase,
+    /// Timestamp of the change
+    pub timestamp: u64,
+}
+
+impl MarketStatus {
+    /// Creates a status of the whole market
+    /// # Example
+    /// ```rust
+    /// use quotes::{MarketStatus, TradingPhase};
+    /// let status = MarketStatus::market(TradingPhase::Open, 1700000000000);
+    /// assert_eq!(status.ticker, None);
+    /// ```
+    pub fn market(phase: TradingPhase, timestamp: u64) -> Self {
+        Self {
+            ticker: None,
+            phase,
+            timestamp,
+        }
+    }
+
+    /// Creates a status of one ticker
+    /// # Example
+    /// ```rust
+    /// use quotes::{MarketStatus, TradingPhase};
+    /// let status = MarketStatus::ticker("AAPL", TradingPhase::Halted, 1700000000000);
+    /// assert_eq!(status.ticker.as_deref(), Some("AAPL"));
+    /// ```
+    pub fn ticker(ticker: &str, phase: TradingPhase, timestamp: u64) -> Self {
+        Self {
+            ticker: Some(ticker.to_string()),
+            phase,
+            timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_json() {
+        let status = MarketStatus::ticker("AAPL", TradingPhase::ClosingAuction, 42);
+        let json = serde_json::to_string(&status).unwrap();
+        assert_eq!(
+            json,
+            r#"{"ticker":"AAPL","phase":"closing_auction","timestamp":42}"#
+        );
+        let market: MarketStatus =
+            serde_json::from_str(r#"{"phase":"open","timestamp":1}"#).unwrap();
+        assert_eq!(market, MarketStatus::market(TradingPhase::Open, 1));
+    }
+}
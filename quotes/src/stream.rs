@@ -0,0 +1,104 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+/// Market data a stream carries besides statuses and corporate actions, written as a comma
+/// separated list of `trades`, `quotes`, `depth` and `prints`, `both` is `trades,quotes`
+/// # Example
+/// ```rust
+/// use quotes::StreamKind;
+/// let kind: StreamKind = "both,depth".parse().unwrap();
+/// assert!(kind.trades && kind.quotes && kind.depth && !kind.prints);
+/// assert_eq!(kind.to_string(), "trades,quotes,depth");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamKind {
+    /// Last trade quotes
+    pub trades: bool,
+    /// Top of the book quotes
+    pub quotes: bool,
+    /// Order book depth updates and snapshots
+    pub depth: bool,
+    /// Individual trade prints
+    pub prints: bool,
+}
+
+impl StreamKind {
+    /// Last trade quotes only, the stream of a request without a kind
+    pub const TRADES: StreamKind = StreamKind {
+        trades: true,
+        quotes: false,
+        depth: false,
+        prints: false,
+    };
+}
+
+impl Default for StreamKind {
+    fn default() -> Self {
+        StreamKind::TRADES
+    }
+}
+
+impl FromStr for StreamKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut kind = StreamKind {
+            trades: false,
+            quotes: false,
+            depth: false,
+            prints: false,
+        };
+        for part in s.split(',') {
+            match part.trim() {
+                "trades" => kind.trades = true,
+                "quotes" => kind.quotes = true,
+                "both" => (kind.trades, kind.quotes) = (true, true),
+                "depth" => kind.depth = true,
+                "prints" => kind.prints = true,
+                part => {
+                    return Err(format!(
+                        "unknown stream kind '{}', expected trades, quotes, both, depth or prints",
+                        part
+                    ));
+                }
+            }
+        }
+        Ok(kind)
+    }
+}
+
+impl fmt::Display for StreamKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let names = [
+            (self.trades, "trades"),
+            (self.quotes, "quotes"),
+            (self.depth, "depth"),
+            (self.prints, "prints"),
+        ];
+        let names: Vec<&str> = names
+            .into_iter()
+            .filter_map(|(set, name)| set.then_some(name))
+            .collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_kind() {
+        assert_eq!("trades".parse::<StreamKind>(), Ok(StreamKind::TRADES));
+        let kind: StreamKind = "quotes,prints".parse().unwrap();
+        assert!(!kind.trades && kind.quotes && !kind.depth && kind.prints);
+        assert_eq!(kind.to_string(), "quotes,prints");
+        assert_eq!(
+            "both".parse::<StreamKind>().unwrap().to_string(),
+            "trades,quotes"
+        );
+        assert!("trades,news".parse::<StreamKind>().is_err());
+        assert!("".parse::<StreamKind>().is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{Price, Side};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// Shares in a round lot, smaller trades are odd lots
+pub const ROUND_LOT: u64 = 100;
+/// Smallest block trade
+pub const BLOCK_SIZE: u64 = 10_000;
+
+/// Special circumstances of a trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeCondition {
+    /// Fewer shares than a round lot
+    OddLot,
+    /// At least `BLOCK_SIZE` shares
+    Block,
+}
+
+impl fmt::Display for TradeCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TradeCondition::OddLot => write!(f, "odd_lot"),
+            TradeCondition::Block => write!(f, "block"),
+        }
+    }
+}
+
+/// One trade print of a ticker
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Trade {
+    /// The stock name
+    pub ticker: String,
+    /// Identifier of the trade, unique across tickers
+    pub trade_id: u64,
+    /// Price of the trade
+    pub price: Price,
+    /// Shares traded
+    pub size: u64,
+    /// Side that crossed the spread: `Bid` for a buyer taking the ask,
+    /// `Ask` for a seller hitting the bid
+    pub aggressor: Side,
+    /// Conditions of the trade, empty for a regular one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<TradeCondition>,
+    /// Timestamp, then the trade happened
+    pub timestamp: u64,
+}
+
+impl Trade {
+    /// Creates a trade of `size` shares, its conditions follow from the size
+    /// # Example
+    /// ```rust
+    /// use quotes::{Price, Side, Trade, TradeCondition};
+    /// let trade = Trade::new("AAPL", 1, Price::from_cents(18000), 50, Side::Bid, 1700000000000);
+    /// assert_eq!(trade.conditions, vec![TradeCondition::OddLot]);
+    /// ```
+    pub fn new(
+        ticker: &str,
+        trade_id: u64,
+        price: Price,
+        size: u64,
+        aggressor: Side,
+        timestamp: u64,
+    ) -> Self {
+        let mut conditions = vec![];
+        if size < ROUND_LOT {
+            conditions.push(TradeCondition::OddLot);
+        }
+        if size >= BLOCK_SIZE {
+            conditions.push(TradeCondition::Block);
+        }
+        Self {
+            ticker: ticker.to_string(),
+            trade_id,
+            price,
+            size,
+            aggressor,
+            conditions,
+            timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trade_json() {
+        let price = Price::from_cents(18000);
+        let trade = Trade::new("AAPL", 7, price, 300, Side::Ask, 42);
+        assert!(trade.conditions.is_empty());
+        let json = serde_json::to_string(&trade).unwrap();
+        assert_eq!(
+            json,
+            r#"{"ticker":"AAPL","trade_id":7,"price":18000,"size":300,"aggressor":"ask","timestamp":42}"#
+        );
+        assert_eq!(serde_json::from_str::<Trade>(&json).unwrap(), trade);
+
+        let block = Trade::new("AAPL", 8, price, BLOCK_SIZE, Side::Bid, 42);
+        assert_eq!(block.conditions, vec![TradeCondition::Block]);
+        let json = serde_json::to_string(&block).unwrap();
+        assert!(json.contains(r#""conditions":["block"]"#));
+        assert_eq!(serde_json::from_str::<Trade>(&json).unwrap(), block);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::Price;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// Highest price a ticker can be configured with, one billion. Far below the largest `Price`,
+/// so the generator scales configured prices without overflowing
+pub const MAX_PRICE: Price = Price::from_cents(100_000_000_000);
+
+/// Highest volume a ticker's volume profile can reach, one trillion shares
+pub const MAX_VOLUME: u64 = 1_000_000_000_000;
+
+/// Inclusive lower and upper bound of a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Bounds<T = u64> {
+    /// Lowest allowed value
+    pub min: T,
+    /// Highest allowed value
+    pub max: T,
+}
+
+/// Settings of one ticker of the universe, unset values are left to the generator
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TickerConfig {
+    /// The stock name
+    pub ticker: String,
+    /// Starting price
+    pub price: Option<Price>,
+    /// Price never leaves these bounds
+    pub price_range: Option<Bounds<Price>>,
+    /// Volume profile: bounds of the traded volume
+    pub volume: Option<Bounds>,
+    /// Relative share of the quotes published for this ticker
+    pub weight: Option<u64>,
+    /// Expected log return per generator step
+    pub drift: Option<f64>,
+    /// Standard deviation of the log return per generator step
+    pub volatility: Option<f64>,
+    /// Prices are multiples of this step
+    pub tick_size: Option<Price>,
+    /// Sector or index whose shared shocks move the ticker
+    pub sector: Option<String>,
+    /// Correlation of the ticker's returns with its sector factor, 0.5 when unset
+    pub sector_correlation: Option<f64>,
+}
+
+impl TickerConfig {
+    /// Creates a `TickerConfig` with every setting left to the generator
+    /// # Example
+    /// ```rust
+    /// use quotes::TickerConfig;
+    /// let config = TickerConfig::new("AAPL");
+    /// assert_eq!(config.weight, None);
+    /// ```
+    pub fn new(ticker: &str) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            price: None,
+            price_range: None,
+            volume: None,
+            weight: None,
+            drift: None,
+            volatility: None,
+            tick_size: None,
+            sector: None,
+            sector_correlation: None,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let invalid = |reason: &str| Err(format!("{}: {}", self.ticker, reason));
+        if self.ticker.is_empty() {
+            return Err("empty ticker".to_string());
+        }
+        if self.price_range.is_some_and(|r| r.min > r.max)
+            || self.volume.is_some_and(|v| v.min > v.max)
+        {
+            return invalid("min is greater than max");
+        }
+        if let (Some(price), Some(range)) = (self.price, self.price_range)
+            && !(range.min..=range.max).contains(&price)
+        {
+            return invalid("price is out of price_range");
+        }
+        let prices = [self.price, self.tick_size, self.price_range.map(|r| r.max)];
+        if prices.into_iter().flatten().any(|price| price > MAX_PRICE) {
+            return invalid("prices must not exceed 1000000000.00");
+        }
+        if self.volume.is_some_and(|v| v.max > MAX_VOLUME) {
+            return invalid("volume must not exceed 1000000000000");
+        }
+        if self.weight == Some(0) {
+            return invalid("weight must be positive");
+        }
+        if self.tick_size.is_some_and(Price::is_zero) {
+            return invalid("tick_size must be positive");
+        }
+        if self.volatility.is_some_and(|v| v < 0.0) {
+            return invalid("volatility must not be negative");
+        }
+        if self
+            .sector_correlation
+            .is_some_and(|c| !(0.0..=1.0).contains(&c))
+        {
+            return invalid("sector_correlation must be within 0..=1");
+        }
+        if self.sector_correlation.is_some() && self.sector.is_none() {
+            return invalid("sector_correlation requires a sector");
+        }
+        Ok(())
+    }
+}
+
+/// Format of a universe file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UniverseFormat {
+    /// One ticker per line, as read by [`parse_tickers`](crate::parse_tickers)
+    List,
+    /// `[[tickers]]` tables
+    Toml,
+    /// `{"tickers": [...]}`
+    Json,
+}
+
+impl UniverseFormat {
+    /// Format of a file with the given extension, `List` for anything but `toml` and `json`
+    /// # Example
+    /// ```rust
+    /// use quotes::UniverseFormat;
+    /// assert_eq!(UniverseFormat::from_extension("toml"), UniverseFormat::Toml);
+    /// assert_eq!(UniverseFormat::from_extension("txt"), UniverseFormat::List);
+    /// ```
+    pub fn from_extension(extension: &str) -> Self {
+        match extension.to_ascii_lowercase().as_str() {
+            "toml" => UniverseFormat::Toml,
+            "json" => UniverseFormat::Json,
+            _ => UniverseFormat::List,
+        }
+    }
+}
+
+/// Correlation of the factor shocks of two sectors
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SectorCorrelation {
+    /// The two sectors
+    pub between: [String; 2],
+    /// Correlation within `-1..=1`
+    pub value: f64,
+}
+
+/// Tickers with their settings and the correlation matrix of their sectors
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Universe {
+    /// Settings of every ticker
+    pub tickers: Vec<TickerConfig>,
+    /// Off-diagonal entries of the sector correlation matrix, missing pairs are uncorrelated
+    #[serde(default)]
+    pub correlations: Vec<SectorCorrelation>,
+}
+
+impl Universe {
+    /// Names of the tickers
+    pub fn names(&self) -> Vec<String> {
+        self.tickers.iter().map(|c| c.ticker.clone()).collect()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        for config in &self.tickers {
+            config.validate()?;
+            if !seen.insert(config.ticker.as_str()) {
+                return Err(format!("{}: listed twice", config.ticker));
+            }
+        }
+        let sectors: HashSet<&str> = self
+            .tickers
+            .iter()
+            .filter_map(|c| c.sector.as_deref())
+            .collect();
+        for correlation in &self.correlations {
+            let [first, second] = &correlation.between;
+            for sector in [first, second] {
+                if !sectors.contains(sector.as_str()) {
+                    return Err(format!("correlation of unknown sector {}", sector));
+                }
+            }
+            if first == second {
+                return Err(format!("correlation of sector {} with itself", first));
+            }
+            if !(-1.0..=1.0).contains(&correlation.value) {
+                return Err(format!(
+                    "correlation of {} and {} must be within -1..=1",
+                    first, second
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read the ticker universe with per-ticker settings. Prices are decimal strings,
+/// integer prices are hundredths
+///
+/// # Example
+/// ```rust
+/// use quotes::{parse_universe, Price, UniverseFormat};
+/// let data = r#"
+/// [[tickers]]
+/// ticker = "AAPL"
+/// price = "180.00"
+/// price_range = { min = "150.00", max = "210.00" }
+/// volume = { min = 1000, max = 50000 }
+/// weight = 10
+/// volatility = 0.001
+/// tick_size = 5
+/// "#;
+/// let universe = parse_universe(data, UniverseFormat::Toml).unwrap();
+/// assert_eq!(universe.tickers[0].tick_size, Some(Price::from_cents(5)));
+/// ```
+pub fn parse_universe(data: &str, format: UniverseFormat) -> Result<Universe, String> {
+    let universe = match format {
+        UniverseFormat::List => Universe {
+            tickers: crate::parse_tickers(data)
+                .iter()
+                .map(|ticker| TickerConfig::new(ticker))
+                .collect(),
+            correlations: vec![],
+        },
+        UniverseFormat::Toml => toml::from_str(data).map_err(|e| e.to_string())?,
+        UniverseFormat::Json => serde_json::from_str(data).map_err(|e| e.to_string())?,
+    };
+    universe.validate()?;
+    Ok(universe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_universe() {
+        let data = r#"{"tickers": [
+            {"ticker": "AAPL", "price": 18000, "weight": 3},
+            {"ticker": "NFLX", "volume": {"min": 10, "max": 20}}
+        ]}"#;
+        let universe = parse_universe(data, UniverseFormat::Json).unwrap().tickers;
+        assert_eq!(universe.len(), 2);
+        assert_eq!(universe[0].price, Some(Price::from_cents(18000)));
+        assert_eq!(universe[0].weight, Some(3));
+        assert_eq!(universe[1].volume, Some(Bounds { min: 10, max: 20 }));
+        assert_eq!(universe[1].price, None);
+    }
+
+    #[test]
+    fn test_parse_list_universe() {
+        let universe = parse_universe("AAPL\nNFLX\n", UniverseFormat::List)
+            .unwrap()
+            .tickers;
+        assert_eq!(
+            universe,
+            vec![TickerConfig::new("AAPL"), TickerConfig::new("NFLX")]
+        );
+    }
+
+    #[test]
+    fn test_invalid_universe() {
+        let toml = |data| parse_universe(data, UniverseFormat::Toml);
+        assert!(toml("[[tickers]]\nticker = \"AAPL\"\nspeed = 1").is_err());
+        assert!(toml("[[tickers]]\nticker = \"AAPL\"\ntick_size = 0").is_err());
+        assert!(
+            toml("[[tickers]]\nticker = \"AAPL\"\nprice = \"5\"\nprice_range = { min = 10, max = 20 }")
+                .is_err()
+        );
+        assert!(toml("[[tickers]]\nticker = \"AAPL\"\n[[tickers]]\nticker = \"AAPL\"").is_err());
+        assert!(toml("[[tickers]]\nticker = \"AAPL\"\nsector_correlation = 0.5").is_err());
+        assert!(toml("[[tickers]]\nticker = \"AAPL\"\nprice = \"1000000000.01\"").is_err());
+        assert!(
+            toml("[[tickers]]\nticker = \"AAPL\"\nprice_range = { min = 1, max = 18446744073709 }")
+                .is_err()
+        );
+        assert!(
+            toml("[[tickers]]\nticker = \"AAPL\"\nvolume = { min = 1, max = 1000000000001 }")
+                .is_err()
+        );
+        assert!(toml("[[tickers]]\nticker = \"AAPL\"\nweight = 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_sector_correlations() {
+        let data = r#"
+            [[tickers]]
+            ticker = "AAPL"
+            sector = "tech"
+            sector_correlation = 0.8
+
+            [[tickers]]
+            ticker = "XOM"
+            sector = "energy"
+
+            [[correlations]]
+            between = ["tech", "energy"]
+            value = 0.3
+        "#;
+        let universe = parse_universe(data, UniverseFormat::Toml).unwrap();
+        assert_eq!(universe.tickers[0].sector.as_deref(), Some("tech"));
+        assert_eq!(universe.correlations[0].value, 0.3);
+
+        let unknown = data.replace("\"energy\"]", "\"utilities\"]");
+        assert!(parse_universe(&unknown, UniverseFormat::Toml).is_err());
+        let invalid = data.replace("0.3", "1.5");
+        assert!(parse_universe(&invalid, UniverseFormat::Toml).is_err());
+    }
+}
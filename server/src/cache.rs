@@ -0,0 +1,81 @@
+use crate::udp::QuoteSink;
+use parking_lot::RwLock;
+use quotes::{CorporateAction, CorporateActionKind, StockQuote};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Last published quote of every ticker
+#[derive(Clone, Default)]
+pub(crate) struct QuoteCache {
+    quotes: Arc<RwLock<HashMap<String, StockQuote>>>,
+}
+
+impl QuoteCache {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Latest quotes of `tickers` in the requested order, or of every ticker when empty
+    pub(crate) fn snapshot(&self, tickers: &[String]) -> Vec<StockQuote> {
+        let quotes = self.quotes.read();
+        if tickers.is_empty() {
+            let mut all: Vec<StockQuote> = quotes.values().cloned().collect();
+            all.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+            return all;
+        }
+        tickers
+            .iter()
+            .filter_map(|ticker| quotes.get(ticker).cloned())
+            .collect()
+    }
+}
+
+impl QuoteSink for QuoteCache {
+    fn record(&self, quote: &StockQuote) {
+        self.quotes
+            .write()
+            .insert(quote.ticker.clone(), quote.clone());
+    }
+
+    /// The last quote of a renamed ticker is kept under its new symbol
+    fn corporate_action(&self, action: &CorporateAction) {
+        let CorporateActionKind::Rename { symbol } = &action.kind else {
+            return;
+        };
+        let mut quotes = self.quotes.write();
+        if let Some(mut quote) = quotes.remove(&action.ticker) {
+            quote.ticker = symbol.clone();
+            quotes.insert(symbol.clone(), quote);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quotes::Price;
+
+    #[test]
+    fn test_snapshot_keeps_last_quote() {
+        let cache = QuoteCache::new();
+        cache.record(&StockQuote::new("AAPL", Price::from_cents(100), 1000));
+        cache.record(&StockQuote::new("NFLX", Price::from_cents(300), 3000));
+        let last = StockQuote::new("AAPL", Price::from_cents(101), 1100);
+        cache.record(&last);
+
+        let snapshot = cache.snapshot(&["AAPL".to_string(), "MSFT".to_string()]);
+        assert_eq!(snapshot, vec![last]);
+        let tickers: Vec<String> = cache.snapshot(&[]).into_iter().map(|q| q.ticker).collect();
+        assert_eq!(tickers, vec!["AAPL", "NFLX"]);
+    }
+
+    #[test]
+    fn test_snapshot_follows_rename() {
+        let cache = QuoteCache::new();
+        cache.record(&StockQuote::new("FB", Price::from_cents(100), 1000));
+        cache.corporate_action(&CorporateAction::rename("FB", "META", 1));
+        let snapshot = cache.snapshot(&["FB".to_string(), "META".to_string()]);
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].ticker, "META");
+    }
+}
@@ -1,10 +1,60 @@
-use crate::udp::ClientCommand;
-use crossbeam::channel::Sender;
-use quotes::StockQuote;
-use rand::rngs::ThreadRng;
-use rand::{Rng, rng};
-use std::thread;
+use crate::generator::book::empty_depth;
+use crate::generator::events::{Action, MarketEvents, ScriptedEvent, Target};
+use crate::generator::factors::Factors;
+use crate::generator::schedule::Schedule;
+use crate::generator::session::{Calendar, TradingHalts};
+use crate::tickers::Tickers;
+use clap::ValueEnum;
+use crossbeam::channel::{Receiver, Sender};
+use log::{info, warn};
+use quotes::{
+    Bounds, Clock, MAX_PRICE, MarketStatus, OrderBook, Price, QuoteSource, SectorCorrelation, Side,
+    SourceEvent, StockQuote, SystemClock, TickerConfig, TradingPhase,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::f64::consts::TAU;
+use std::str::FromStr;
 use std::time::Duration;
+use std::{io, mem};
+
+mod book;
+mod corporate;
+pub(crate) mod events;
+mod factors;
+pub(crate) mod schedule;
+pub(crate) mod session;
+#[cfg(test)]
+mod test_helpers;
+mod trades;
+
+/// Correlation with the sector factor of tickers that do not set one
+const DEFAULT_SECTOR_CORRELATION: f64 = 0.5;
+
+/// How often a closed market checks whether the session has started
+const CLOSED_POLL: Duration = Duration::from_millis(100);
+/// Longest pause without checking for injected market events
+const EVENT_POLL: Duration = Duration::from_millis(100);
+/// Most injected market events waiting for the generator to pick them up
+const MAX_INJECTED_EVENTS: usize = 1024;
+
+/// Starting prices of tickers without a configured price
+const DEFAULT_PRICE: Bounds<Price> = Bounds {
+    min: Price::from_cents(100),
+    max: Price::from_cents(50000),
+};
+/// Tick size of tickers without a configured one
+const DEFAULT_TICK: Price = Price::from_cents(1);
+/// Volume profile of tickers without a configured one
+const DEFAULT_VOLUME: Bounds = Bounds {
+    min: 100_000,
+    max: 10_000_000,
+};
+
+/// Quoted sizes are whole lots of this many shares
+const LOT_SIZE: u64 = 100;
 
 /// Maximum growth of 0.02% to emulate growth
 const MAX_CHANGE: u64 = 10002;
@@ -13,134 +63,1382 @@ const MIN_CHANGE: u64 = 9999;
 /// 100%
 const DIVIDER: u64 = 10000;
 
-pub(crate) fn run(stocks: Vec<String>, stock_tx: Sender<ClientCommand>) {
-    thread::spawn(move || {
-        let mut generator = QuoteGenerator::new(stocks);
-        loop {
-            thread::sleep(Duration::from_millis(10));
-            if let Some(random) = generator.random() {
-                let _ = stock_tx.send(ClientCommand::Send(random));
+/// How a ticker's price moves from one quote to the next
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum PriceModel {
+    /// Uniform change between -0.01% and +0.02%
+    Uniform,
+    /// Geometric Brownian motion
+    #[default]
+    Gbm,
+    /// Ornstein-Uhlenbeck process on the log price, pulled towards `mean`
+    MeanReverting,
+}
+
+/// Price model parameters, rates are per quote of a ticker. Rates of a ticker in a sector
+/// are per generator step instead: every published quote of any ticker is a step, and
+/// a sector ticker quoted after `k` steps moves as much as over `k` steps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ModelParams {
+    pub(crate) drift: f64,
+    pub(crate) volatility: f64,
+    /// Share of the distance to `mean` recovered on each step
+    pub(crate) reversion: f64,
+    /// Price the mean-reverting model is pulled towards, the starting price when unset
+    pub(crate) mean: Option<Price>,
+    /// Starting price, random when unset
+    pub(crate) price: Option<Price>,
+}
+
+impl Default for ModelParams {
+    fn default() -> Self {
+        Self {
+            drift: 0.0,
+            volatility: 0.0005,
+            reversion: 0.01,
+            mean: None,
+            price: None,
+        }
+    }
+}
+
+/// Model parameters of one ticker, parsed from `AAPL:price=180.00,volatility=0.001`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TickerParams {
+    pub(crate) ticker: String,
+    drift: Option<f64>,
+    volatility: Option<f64>,
+    reversion: Option<f64>,
+    mean: Option<Price>,
+    price: Option<Price>,
+}
+
+impl TickerParams {
+    /// `defaults` with the values set for this ticker
+    pub(crate) fn apply(&self, defaults: ModelParams) -> ModelParams {
+        ModelParams {
+            drift: self.drift.unwrap_or(defaults.drift),
+            volatility: self.volatility.unwrap_or(defaults.volatility),
+            reversion: self.reversion.unwrap_or(defaults.reversion),
+            mean: self.mean.or(defaults.mean),
+            price: self.price.or(defaults.price),
+        }
+    }
+}
+
+impl FromStr for TickerParams {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ticker, values) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected TICKER:key=value,..., got '{}'", s))?;
+        let mut params = TickerParams {
+            ticker: ticker.trim().to_string(),
+            drift: None,
+            volatility: None,
+            reversion: None,
+            mean: None,
+            price: None,
+        };
+        for pair in values.split(',').filter(|p| !p.trim().is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", pair))?;
+            let value = value.trim();
+            let key = key.trim();
+            match key {
+                "drift" => params.drift = Some(parse_value(key, value)?),
+                "volatility" => {
+                    let parsed = parse_non_negative(value).map_err(|_| {
+                        format!(
+                            "invalid {} '{}', expected a non-negative number",
+                            key, value
+                        )
+                    })?;
+                    params.volatility = Some(parsed);
+                }
+                "reversion" => {
+                    let parsed = parse_reversion(value)
+                        .map_err(|_| format!("invalid {} '{}', expected 0..=1", key, value))?;
+                    params.reversion = Some(parsed);
+                }
+                "mean" => params.mean = Some(parse_price(key, value)?),
+                "price" => params.price = Some(parse_price(key, value)?),
+                key => return Err(format!("unknown parameter '{}'", key)),
             }
         }
-    });
+        Ok(params)
+    }
 }
 
-struct QuoteGenerator {
-    stocks_rng: ThreadRng,
-    prices_rng: ThreadRng,
-    volumes_rng: ThreadRng,
-    stocks: Vec<StockQuote>,
+fn parse_value(key: &str, value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(parsed) if parsed.is_finite() => Ok(parsed),
+        _ => Err(format!("invalid {} '{}'", key, value)),
+    }
+}
+
+/// Parses a volatility, it is never negative
+pub(crate) fn parse_non_negative(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(value) if value >= 0.0 && value.is_finite() => Ok(value),
+        _ => Err(format!(
+            "invalid value '{}', expected a non-negative number",
+            s
+        )),
+    }
+}
+
+/// Parses a reversion, a share of the distance to the mean within `0..=1`
+pub(crate) fn parse_reversion(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err(format!(
+            "invalid value '{}', expected a number within 0..=1",
+            s
+        )),
+    }
+}
+
+fn parse_price(key: &str, value: &str) -> Result<Price, String> {
+    let price = Price::parse_cents_or_decimal(value)?;
+    if price.is_zero() || price > MAX_PRICE {
+        return Err(format!(
+            "{} must be positive and at most {}, got '{}'",
+            key, MAX_PRICE, value
+        ));
+    }
+    Ok(price)
+}
+
+/// Generator state of one ticker
+struct Ticker {
+    config: TickerConfig,
+    quote: StockQuote,
+    /// Unrounded price in `Price` units, so small moves are not lost to rounding
+    price: f64,
+    /// Price the mean-reverting model is pulled towards, in `Price` units
+    mean: f64,
+    params: ModelParams,
+    /// Index of the sector factor and the correlation with it
+    sector: Option<(usize, f64)>,
+    /// Sector factor level at the last quote
+    factor_mark: f64,
+    /// Generator step of the last quote
+    last_step: u64,
+    /// Simulated depth as last published
+    depth: OrderBook,
+    /// Shares traded by one move, a random walk within the volume profile
+    activity: u64,
+    /// Shares traded by the last move, not printed as trades yet
+    unprinted: u64,
+    /// Side that moved the price of the last quote, `None` if it did not move
+    aggressor: Option<Side>,
+}
+
+impl Ticker {
+    /// Keeps `price` within the configured range and publishes the nearest tick within it
+    fn set_price(&mut self, price: f64, volume: u64, timestamp: u64) {
+        self.price = match self.config.price_range {
+            Some(range) => price.clamp(
+                range.min.units().max(1) as f64,
+                range.max.units().max(1) as f64,
+            ),
+            None => price,
+        };
+        let price = quantize(&self.config, self.price);
+        self.quote.update_at(price, volume, timestamp);
+    }
+}
+
+/// Random quotes for a list of tickers
+pub(crate) struct QuoteGenerator {
+    rng: StdRng,
+    clock: Box<dyn Clock>,
+    model: PriceModel,
+    schedule: Schedule,
+    /// Trading hours, always open when unset
+    calendar: Option<Calendar>,
+    /// Last published market phase
+    phase: Option<TradingPhase>,
+    halts: TradingHalts,
+    /// Halted tickers as last published
+    halted: HashSet<String>,
+    /// Events published before the next quote
+    pending: VecDeque<SourceEvent>,
+    /// Time left before the next quote is published
+    wait: Duration,
+    events_tx: Sender<ScriptedEvent>,
+    events_rx: Receiver<ScriptedEvent>,
+    /// Scenario events not scheduled yet, their delays count from the first step
+    scenario: Vec<ScriptedEvent>,
+    /// Received scripted events by the time they are due
+    scheduled: Vec<(u64, ScriptedEvent)>,
+    /// Frozen tickers and the time their quotes resume
+    frozen: HashMap<String, u64>,
+    /// Published tickers, renamed by corporate actions
+    tickers: Tickers,
+    /// Sector names, in the order of their factors
+    sectors: Vec<String>,
+    factors: Factors,
+    /// Quotes generated so far
+    step: u64,
+    /// Depth levels per side, no depth is published when zero
+    depth_levels: usize,
+    /// How often full depth snapshots are published
+    snapshot_interval: Duration,
+    /// Time the last depth snapshots were published
+    last_snapshot: Option<u64>,
+    /// Identifier of the last printed trade
+    trade_id: u64,
+    stocks: Vec<Ticker>,
 }
 
 impl QuoteGenerator {
-    fn new(stocks: Vec<String>) -> Self {
-        Self {
-            stocks_rng: rand::rng(),
-            prices_rng: rand::rng(),
-            volumes_rng: rand::rng(),
-            stocks: Self::build_stocks(stocks),
+    pub(crate) fn new(stocks: Vec<TickerConfig>) -> Self {
+        Self::with_rng(stocks, StdRng::from_os_rng())
+    }
+
+    /// Generator whose whole output is determined by `seed`
+    pub(crate) fn seeded(stocks: Vec<TickerConfig>, seed: u64) -> Self {
+        Self::with_rng(stocks, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(stocks: Vec<TickerConfig>, rng: StdRng) -> Self {
+        let (events_tx, events_rx) = crossbeam::channel::bounded(MAX_INJECTED_EVENTS);
+        let mut generator = Self {
+            rng,
+            clock: Box::new(SystemClock),
+            model: PriceModel::default(),
+            schedule: Schedule::default(),
+            calendar: None,
+            phase: None,
+            halts: TradingHalts::new(),
+            halted: HashSet::new(),
+            pending: VecDeque::new(),
+            wait: Duration::ZERO,
+            events_tx,
+            events_rx,
+            scenario: vec![],
+            scheduled: vec![],
+            frozen: HashMap::new(),
+            tickers: Tickers::default(),
+            sectors: vec![],
+            factors: Factors::independent(0),
+            step: 0,
+            depth_levels: 0,
+            snapshot_interval: Duration::ZERO,
+            last_snapshot: None,
+            trade_id: 0,
+            stocks: vec![],
+        };
+        generator.stocks = generator.build_stocks(stocks);
+        generator.tickers = Tickers::new(
+            generator
+                .stocks
+                .iter()
+                .map(|s| s.quote.ticker.clone())
+                .collect(),
+        );
+        generator.factors = Factors::independent(generator.sectors.len());
+        generator
+    }
+
+    /// Correlates the sector factors, sectors missing from `correlations` stay independent
+    pub(crate) fn with_correlations(
+        mut self,
+        correlations: &[SectorCorrelation],
+    ) -> io::Result<Self> {
+        self.factors = Factors::new(&self.sectors, correlations)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(self)
+    }
+
+    /// Paces published quotes by `schedule`
+    pub(crate) fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Follows `calendar` trading hours and halts of `halts`
+    pub(crate) fn with_session(mut self, calendar: Option<Calendar>, halts: TradingHalts) -> Self {
+        self.calendar = calendar;
+        self.halts = halts;
+        self
+    }
+
+    /// Publishes `levels` of depth per side after every top of the book quote,
+    /// and the full depth of every ticker each `snapshot_interval`
+    pub(crate) fn with_depth(mut self, levels: usize, snapshot_interval: Duration) -> Self {
+        self.depth_levels = levels;
+        self.snapshot_interval = snapshot_interval;
+        self
+    }
+
+    /// Replaces the clock that drives the trading session, scripted events and quote
+    /// timestamps, e.g. with a [`quotes::FixedClock`] for reproducible runs
+    pub(crate) fn with_clock(mut self, clock: Box<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Tickers currently published, following renames
+    pub(crate) fn tickers(&self) -> Tickers {
+        self.tickers.clone()
+    }
+
+    /// Handle injecting scripted events into this generator
+    pub(crate) fn events(&self) -> MarketEvents {
+        MarketEvents {
+            tx: self.events_tx.clone(),
+            tickers: self.tickers.clone(),
+            sectors: self.sectors.iter().cloned().collect(),
+        }
+    }
+
+    /// Schedules the events of a scenario, failing on the first one that does not pass
+    /// [`ScriptedEvent::check`]
+    pub(crate) fn script(&mut self, scenario: Vec<ScriptedEvent>) -> Result<(), String> {
+        for event in &scenario {
+            self.check(event)?;
         }
+        self.scenario.extend(scenario);
+        Ok(())
+    }
+
+    fn check(&self, event: &ScriptedEvent) -> Result<(), String> {
+        event.check(&self.tickers, |sector| {
+            self.sectors.iter().any(|s| s == sector)
+        })
     }
 
-    fn build_stocks(tickers: Vec<String>) -> Vec<StockQuote> {
+    fn build_stocks(&mut self, tickers: Vec<TickerConfig>) -> Vec<Ticker> {
         tickers
-            .iter()
-            .enumerate()
-            .map(|(i, ticker)| Self::random_stock(ticker, i as u64))
+            .into_iter()
+            .map(|config| {
+                let quote = self.random_stock(&config);
+                let sector = config.sector.as_ref().map(|sector| {
+                    let correlation = config
+                        .sector_correlation
+                        .unwrap_or(DEFAULT_SECTOR_CORRELATION);
+                    (self.sector_index(sector), correlation)
+                });
+                Ticker {
+                    price: quote.price.units() as f64,
+                    mean: quote.price.units() as f64,
+                    depth: empty_depth(&quote.ticker),
+                    activity: quote.volume,
+                    unprinted: 0,
+                    aggressor: None,
+                    quote,
+                    params: ModelParams::default(),
+                    sector,
+                    factor_mark: 0.0,
+                    last_step: 0,
+                    config,
+                }
+            })
             .collect()
     }
 
-    fn random(&mut self) -> Option<StockQuote> {
-        if self.stocks.is_empty() {
-            return None;
+    fn sector_index(&mut self, sector: &str) -> usize {
+        match self.sectors.iter().position(|s| s == sector) {
+            Some(index) => index,
+            None => {
+                self.sectors.push(sector.to_string());
+                self.sectors.len() - 1
+            }
+        }
+    }
+
+    /// Moves prices with `model`. Parameters of a ticker are taken from `params`,
+    /// then from its universe settings, then from `defaults`
+    pub(crate) fn with_model(
+        mut self,
+        model: PriceModel,
+        defaults: ModelParams,
+        params: &[TickerParams],
+    ) -> Self {
+        let params: HashMap<&str, &TickerParams> =
+            params.iter().map(|p| (p.ticker.as_str(), p)).collect();
+        self.model = model;
+        for stock in self.stocks.iter_mut() {
+            let configured = ModelParams {
+                drift: stock.config.drift.unwrap_or(defaults.drift),
+                volatility: stock.config.volatility.unwrap_or(defaults.volatility),
+                price: stock.config.price.or(defaults.price),
+                ..defaults
+            };
+            stock.params = match params.get(stock.quote.ticker.as_str()) {
+                Some(params) => params.apply(configured),
+                None => configured,
+            };
+            if let Some(price) = stock.params.price {
+                stock.quote.price = quantize(&stock.config, price.units() as f64);
+                stock.price = stock.quote.price.units() as f64;
+            }
+            stock.mean = stock.params.mean.unwrap_or(stock.quote.price).units() as f64;
+        }
+        self
+    }
+
+    /// Continues tickers found in `last_quotes` from their last price
+    pub(crate) fn resume(&mut self, mut last_quotes: HashMap<String, StockQuote>) {
+        for stock in self.stocks.iter_mut() {
+            if let Some(last) = last_quotes.remove(&stock.quote.ticker) {
+                stock.quote.update(last.price, last.volume);
+                stock.price = last.price.units() as f64;
+                stock.mean = stock.params.mean.unwrap_or(last.price).units() as f64;
+            }
         }
-        let idx = self.random_idx();
-        let mut stock = self.stocks.get(idx).cloned()?;
-        self.correct_stock(&mut stock);
-        self.stocks[idx] = stock.clone();
-        Some(stock)
     }
 
-    fn random_idx(&mut self) -> usize {
-        let len = self.stocks.len();
-        let total_weight: usize = len * (len + 1) / 2;
-        let mut random_weight = self.stocks_rng.random_range(0..total_weight);
-        for i in 0..len {
-            let weight = len - i;
+    fn random(&mut self) -> Option<StockQuote> {
+        self.random_step().map(|idx| self.stocks[idx].quote.clone())
+    }
+
+    /// Moves a random ticker, returns its index
+    fn random_step(&mut self) -> Option<usize> {
+        let idx = self.random_idx()?;
+        self.advance();
+        self.correct_stock(idx);
+        Some(idx)
+    }
+
+    /// Picks a ticker with probability proportional to its weight
+    fn random_idx(&mut self) -> Option<usize> {
+        let total_weight = self
+            .stocks
+            .iter()
+            .map(|s| self.weight(s))
+            .fold(0, u64::saturating_add);
+        if total_weight == 0 {
+            return None;
+        }
+        let mut random_weight = self.rng.random_range(0..total_weight);
+        for (i, stock) in self.stocks.iter().enumerate() {
+            let weight = self.weight(stock);
             if random_weight < weight {
-                return i;
+                return Some(i);
             }
             random_weight -= weight;
         }
-        0
+        None
     }
 
-    fn random_stock(ticker: &str, weight: u64) -> StockQuote {
-        StockQuote::new(ticker, Self::random_price(), Self::random_volume(weight))
+    /// Halted and frozen tickers are never picked
+    fn weight(&self, stock: &Ticker) -> u64 {
+        if self.halted.contains(&stock.quote.ticker)
+            || self.frozen.contains_key(&stock.quote.ticker)
+        {
+            return 0;
+        }
+        stock.config.weight.unwrap_or(1)
     }
 
-    fn random_price() -> u64 {
-        rng().random_range(100..50000)
+    /// Queues status messages for session and halt changes since the last call
+    fn update_session(&mut self, now: u64) {
+        if let Some(calendar) = self.calendar {
+            let phase = calendar.phase_at(now);
+            if self.phase != Some(phase) {
+                let previous = self.phase.replace(phase);
+                if matches!(
+                    previous,
+                    Some(TradingPhase::PreOpen | TradingPhase::ClosingAuction)
+                ) {
+                    self.uncross(now);
+                }
+                self.pending
+                    .push_back(SourceEvent::Status(MarketStatus::market(phase, now)));
+            }
+        }
+
+        let halted = self.halts.halted();
+        if halted == self.halted {
+            return;
+        }
+        let phase = self.phase.unwrap_or(TradingPhase::Open);
+        for stock in &self.stocks {
+            let ticker = &stock.quote.ticker;
+            let status = match (self.halted.contains(ticker), halted.contains(ticker)) {
+                (false, true) => TradingPhase::Halted,
+                (true, false) => phase,
+                _ => continue,
+            };
+            self.pending
+                .push_back(SourceEvent::Status(MarketStatus::ticker(
+                    ticker, status, now,
+                )));
+        }
+        self.halted = halted;
     }
 
-    fn random_volume(mut weight: u64) -> u64 {
-        if weight == 0 {
-            weight = 1;
+    /// Auction result: the last price of every trading ticker with its volume
+    fn uncross(&mut self, now: u64) {
+        for stock in &self.stocks {
+            if self.halted.contains(&stock.quote.ticker) {
+                continue;
+            }
+            let mut quote = stock.quote.clone();
+            quote.timestamp = now;
+            self.pending.push_back(SourceEvent::Quote(quote));
         }
-        let max = u32::MAX as u64 / weight;
-        let min = max / 2;
-        rng().random_range(min..max)
     }
 
-    fn correct_stock(&mut self, stock_quote: &mut StockQuote) {
-        stock_quote.update(
-            self.correct_price(stock_quote.price),
-            self.correct_volume(stock_quote.volume),
-        );
+    /// Applies scripted events due by `now`, price moves are published right away
+    fn run_events(&mut self, now: u64) {
+        let received: Vec<ScriptedEvent> = mem::take(&mut self.scenario)
+            .into_iter()
+            .chain(self.events_rx.try_iter())
+            .collect();
+        for event in received {
+            let due = now + event.delay.as_millis() as u64;
+            let at = self.scheduled.partition_point(|(d, _)| *d <= due);
+            self.scheduled.insert(at, (due, event));
+        }
+        self.frozen.retain(|_, until| *until > now);
+        let due = self.scheduled.partition_point(|(d, _)| *d <= now);
+        let events: Vec<(u64, ScriptedEvent)> = self.scheduled.drain(..due).collect();
+        for (_, event) in events {
+            if let Err(e) = self.check(&event) {
+                warn!("Skipped market event {}: {}", event, e);
+                continue;
+            }
+            info!("Market event: {}", event);
+            self.apply(&event, now);
+        }
+    }
+
+    fn apply(&mut self, event: &ScriptedEvent, now: u64) {
+        let phase = self.phase.unwrap_or(TradingPhase::Open);
+        let mut moved = vec![];
+        for idx in 0..self.stocks.len() {
+            let stock = &mut self.stocks[idx];
+            let ticker = stock.quote.ticker.clone();
+            let targeted = match &event.target {
+                Target::Ticker(target) => *target == ticker,
+                Target::Sector(sector) => stock.config.sector.as_ref() == Some(sector),
+                Target::All => true,
+            };
+            if !targeted {
+                continue;
+            }
+            match &event.action {
+                Action::Move(change) => {
+                    let price = stock.price * (1.0 + *change as f64 / DIVIDER as f64);
+                    stock.set_price(price, stock.quote.volume, now);
+                }
+                Action::Freeze(duration) => {
+                    let until = now + duration.as_millis() as u64;
+                    self.frozen.insert(ticker, until);
+                    continue;
+                }
+                Action::Split {
+                    new_shares,
+                    old_shares,
+                } => self.split_ticker(idx, *new_shares, *old_shares, now),
+                Action::Rename(symbol) => {
+                    self.rename_ticker(idx, symbol, now);
+                    continue;
+                }
+            }
+            if phase == TradingPhase::Closed
+                || self.halted.contains(&ticker)
+                || self.frozen.contains_key(&ticker)
+            {
+                continue;
+            }
+            let mut quote = self.stocks[idx].quote.clone();
+            if phase == TradingPhase::Open {
+                moved.push(idx);
+            } else {
+                quote.volume = 0;
+            }
+            self.pending.push_back(SourceEvent::Quote(quote));
+        }
+        for idx in moved {
+            self.publish_book(idx);
+        }
+    }
+
+    fn random_stock(&mut self, config: &TickerConfig) -> StockQuote {
+        let price = match config.price {
+            Some(price) => price,
+            None => self.random_price(config),
+        };
+        let price = quantize(config, price.units() as f64);
+        let volume = self.random_in(config.volume.unwrap_or(DEFAULT_VOLUME));
+        let mut stock = StockQuote::new(&config.ticker, price, volume);
+        stock.timestamp = self.clock.now();
+        stock
+    }
+
+    fn random_in(&mut self, bounds: Bounds) -> u64 {
+        self.rng.random_range(bounds.min..=bounds.max)
+    }
+
+    /// Whole number of ticks within the ticker's price range
+    fn random_price(&mut self, config: &TickerConfig) -> Price {
+        let range = config.price_range.unwrap_or(DEFAULT_PRICE);
+        let tick = config.tick_size.unwrap_or(DEFAULT_TICK);
+        tick * self.random_in(tick_bounds(range, tick))
     }
 
-    fn correct_price(&mut self, price: u64) -> u64 {
-        let correct: u64 = self.prices_rng.random_range(MIN_CHANGE..MAX_CHANGE);
-        (price * correct).div_ceil(DIVIDER)
+    fn correct_stock(&mut self, idx: usize) {
+        let price = match self.model {
+            PriceModel::Uniform => self.correct_price(self.stocks[idx].quote.price),
+            PriceModel::Gbm => self.gbm_price(idx),
+            PriceModel::MeanReverting => self.mean_reverting_price(idx),
+        };
+        let activity = self.correct_volume(self.stocks[idx].activity);
+        let timestamp = self.clock.now();
+        let stock = &mut self.stocks[idx];
+        stock.activity = clamp(activity, stock.config.volume.unwrap_or(DEFAULT_VOLUME));
+        stock.unprinted = stock.activity;
+        let previous = stock.quote.clone();
+        stock.set_price(price, previous.volume, timestamp);
+        stock.aggressor = match stock.quote.price.cmp(&previous.price) {
+            Ordering::Greater => Some(Side::Bid),
+            Ordering::Less => Some(Side::Ask),
+            Ordering::Equal => None,
+        };
+    }
+
+    /// Rounds the changed price up to whole hundredths, as the uniform model always did
+    fn correct_price(&mut self, price: Price) -> f64 {
+        let correct: u64 = self.rng.random_range(MIN_CHANGE..MAX_CHANGE);
+        // In u128, a price times the correction may not fit u64
+        let cent = u128::from(Price::from_cents(1).units());
+        let cents =
+            (u128::from(price.units()) * u128::from(correct)).div_ceil(u128::from(DIVIDER) * cent);
+        (cents * cent) as f64
+    }
+
+    /// Moves to the next step, the sector factors receive their shocks
+    fn advance(&mut self) {
+        self.step += 1;
+        if !self.factors.is_empty() {
+            let rng = &mut self.rng;
+            self.factors.advance(|| standard_normal(rng));
+        }
+    }
+
+    /// Shock of the ticker and the steps it covers. A ticker without a sector moves by one
+    /// step per quote. A sector ticker moves by the steps since its last quote, the shock is
+    /// normal with variance `steps` and its sector factor share is correlated across tickers
+    fn shock(&mut self, idx: usize) -> (f64, f64) {
+        let z = standard_normal(&mut self.rng);
+        let step = self.step;
+        let stock = &mut self.stocks[idx];
+        let steps = step.saturating_sub(stock.last_step) as f64;
+        stock.last_step = step;
+        let Some((sector, correlation)) = stock.sector else {
+            return (z, 1.0);
+        };
+        let level = self.factors.level(sector);
+        let common = level - mem::replace(&mut stock.factor_mark, level);
+        let own = (1.0 - correlation * correlation).sqrt() * steps.sqrt() * z;
+        (correlation * common + own, steps)
+    }
+
+    /// `S' = S * exp(k * (drift - volatility^2 / 2) + volatility * Z)` over `k` steps
+    fn gbm_price(&mut self, idx: usize) -> f64 {
+        let (shock, steps) = self.shock(idx);
+        let stock = &self.stocks[idx];
+        let (drift, volatility) = (stock.params.drift, stock.params.volatility);
+        stock.price * (steps * (drift - volatility * volatility / 2.0) + volatility * shock).exp()
+    }
+
+    /// `ln S' = ln S + (1 - (1 - reversion)^k) * (ln mean - ln S) + volatility * Z` over `k` steps
+    fn mean_reverting_price(&mut self, idx: usize) -> f64 {
+        let (shock, steps) = self.shock(idx);
+        let stock = &self.stocks[idx];
+        let (volatility, reversion) = (stock.params.volatility, stock.params.reversion);
+        let pull = 1.0 - (1.0 - reversion).powf(steps);
+        let (log_price, log_mean) = (stock.price.ln(), stock.mean.ln());
+        (log_price + pull * (log_mean - log_price) + volatility * shock).exp()
     }
 
     fn correct_volume(&mut self, volume: u64) -> u64 {
-        let correct: u64 = self.volumes_rng.random_range(MIN_CHANGE..MAX_CHANGE);
-        (volume * correct).div_ceil(DIVIDER)
+        let correct: u64 = self.rng.random_range(MIN_CHANGE..MAX_CHANGE);
+        volume.saturating_mul(correct).div_ceil(DIVIDER)
+    }
+}
+
+/// Box-Muller transform of two uniform samples
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.random::<f64>();
+    let u2: f64 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
+
+/// Tick size of the ticker in `Price` units
+fn tick_units(config: &TickerConfig) -> u64 {
+    config.tick_size.unwrap_or(DEFAULT_TICK).units().max(1)
+}
+
+/// Rounds `price` in `Price` units to the ticker's tick size, never below one tick.
+/// A price outside the ticker's range becomes its nearest tick within the range
+fn quantize(config: &TickerConfig, price: f64) -> Price {
+    let tick_size = config.tick_size.unwrap_or(DEFAULT_TICK);
+    let price = Price::from_units(price.round() as u64).round_to_tick(tick_size);
+    match config.price_range {
+        Some(range) if !tick_size.is_zero() => {
+            tick_size
+                * clamp(
+                    price.units() / tick_size.units(),
+                    tick_bounds(range, tick_size),
+                )
+        }
+        _ => price,
+    }
+}
+
+/// Numbers of ticks within `range`, at least one tick even if the range has none
+fn tick_bounds(range: Bounds<Price>, tick: Price) -> Bounds {
+    let min = range.min.units().div_ceil(tick.units()).max(1);
+    let max = (range.max.units() / tick.units()).max(min);
+    Bounds { min, max }
+}
+
+fn clamp(value: u64, bounds: Bounds) -> u64 {
+    value.clamp(bounds.min, bounds.max)
+}
+
+impl QuoteSource for QuoteGenerator {
+    /// Quotes regardless of the trading session
+    fn next_quote(&mut self) -> Option<(Duration, StockQuote)> {
+        self.random()
+            .map(|quote| (self.schedule.next_delay(), quote))
+    }
+
+    /// Quotes during trading hours, each followed by its trades and the top of the book,
+    /// and indicative ones without volume during auctions
+    /// Waits for the next quote in steps of at most `EVENT_POLL` and never past the next
+    /// scheduled event, so events are applied on time however slow the quotes are
+    fn next_event(&mut self) -> Option<(Duration, SourceEvent)> {
+        let mut delay = EVENT_POLL;
+        if self.pending.is_empty() {
+            let now = self.clock.now();
+            self.update_session(now);
+            self.run_events(now);
+            self.snapshot_depth(now);
+            if let Some((due, _)) = self.scheduled.first() {
+                delay = delay.min(Duration::from_millis(due.saturating_sub(now)));
+            }
+        }
+        if let Some(event) = self.pending.pop_front() {
+            return Some((Duration::ZERO, event));
+        }
+        let phase = self.phase.unwrap_or(TradingPhase::Open);
+        if phase == TradingPhase::Closed {
+            return Some((CLOSED_POLL.min(delay), SourceEvent::Idle));
+        }
+        if self.wait.is_zero() {
+            self.wait = self.schedule.next_delay();
+        }
+        if self.wait > delay {
+            self.wait -= delay;
+            return Some((delay, SourceEvent::Idle));
+        }
+        let delay = mem::take(&mut self.wait);
+        let Some(idx) = self.random_step() else {
+            return Some((delay, SourceEvent::Idle));
+        };
+        if phase == TradingPhase::Open {
+            self.publish_trades(idx);
+        }
+        let mut quote = self.stocks[idx].quote.clone();
+        if phase == TradingPhase::Open {
+            self.publish_book(idx);
+        } else {
+            quote.volume = 0;
+        }
+        Some((delay, SourceEvent::Quote(quote)))
     }
 }
 
 impl Default for QuoteGenerator {
     fn default() -> Self {
-        Self {
-            stocks_rng: rand::rng(),
-            prices_rng: rand::rng(),
-            volumes_rng: rand::rng(),
-            stocks: vec![],
-        }
+        Self::new(vec![])
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generator::test_helpers::{seeded, universe};
+    use quotes::{CorporateAction, FixedClock, UniverseFormat, parse_universe};
 
     #[test]
     fn test_random_stock() {
-        let mut generator = QuoteGenerator::new(vec!["APPL".to_string()]);
+        let mut generator = QuoteGenerator::new(universe(&["APPL"]));
         let mut prev = generator.random().unwrap();
         for _ in 0..1000 {
             let curr = generator.random().unwrap();
             if prev.ticker == curr.ticker {
                 if prev.price > curr.price {
                     let difference = prev.price - curr.price;
-                    assert!(difference <= prev.price.div_ceil(100));
+                    assert!(difference.units() <= prev.price.units().div_ceil(100));
                 } else {
                     let difference = curr.price - prev.price;
-                    assert!(difference <= (prev.price * 2).div_ceil(100));
+                    assert!(difference.units() <= (prev.price * 2).units().div_ceil(100));
                 }
             }
             prev = curr;
         }
     }
+
+    #[test]
+    fn test_seeded_sequence_is_reproducible() {
+        let mut first = seeded(42);
+        let mut second = seeded(42);
+        let first: Vec<StockQuote> = (0..100).map(|_| first.random().unwrap()).collect();
+        let second: Vec<StockQuote> = (0..100).map(|_| second.random().unwrap()).collect();
+        assert_eq!(first, second);
+        assert_eq!(first[0].timestamp, 1_000_000);
+        assert_eq!(first[99].timestamp, 1_000_990);
+
+        let mut other = seeded(43);
+        let other: Vec<StockQuote> = (0..100).map(|_| other.random().unwrap()).collect();
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_seeded_exact_prices() {
+        let mut generator = seeded(7).with_model(PriceModel::Uniform, ModelParams::default(), &[]);
+        let quotes: Vec<(String, Price)> = (0..5)
+            .map(|_| generator.random().unwrap())
+            .map(|q| (q.ticker, q.price))
+            .collect();
+        let expected = [
+            ("AAPL", 1612),
+            ("NFLX", 13697),
+            ("AAPL", 1612),
+            ("NFLX", 13699),
+            ("MSFT", 7217),
+        ];
+        let expected: Vec<(String, Price)> = expected
+            .iter()
+            .map(|(ticker, price)| (ticker.to_string(), Price::from_cents(*price)))
+            .collect();
+        assert_eq!(quotes, expected);
+    }
+
+    /// Log returns of `count` quotes of the single ticker of `generator`
+    fn log_returns(generator: &mut QuoteGenerator, count: usize) -> Vec<f64> {
+        let mut prices = vec![generator.stocks[0].price];
+        for _ in 0..count {
+            generator.random().unwrap();
+            prices.push(generator.stocks[0].price);
+        }
+        prices.windows(2).map(|p| (p[1] / p[0]).ln()).collect()
+    }
+
+    fn mean_and_deviation(values: &[f64]) -> (f64, f64) {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance =
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+        (mean, variance.sqrt())
+    }
+
+    #[test]
+    fn test_gbm_returns_match_parameters() {
+        let (drift, volatility) = (0.001, 0.01);
+        let params = ModelParams {
+            drift,
+            volatility,
+            price: Some(Price::from_cents(1_000_000)),
+            ..ModelParams::default()
+        };
+        let mut generator =
+            QuoteGenerator::seeded(universe(&["AAPL"]), 1).with_model(PriceModel::Gbm, params, &[]);
+        let count = 20_000;
+        let returns = log_returns(&mut generator, count);
+        let (mean, deviation) = mean_and_deviation(&returns);
+
+        let standard_error = volatility / (count as f64).sqrt();
+        let expected = drift - volatility * volatility / 2.0;
+        assert!(
+            (mean - expected).abs() < 4.0 * standard_error,
+            "mean {} expected {}",
+            mean,
+            expected
+        );
+        assert!(
+            (deviation / volatility - 1.0).abs() < 0.05,
+            "deviation {} expected {}",
+            deviation,
+            volatility
+        );
+    }
+
+    #[test]
+    fn test_mean_reverting_returns_to_mean() {
+        let params = "AAPL:price=20000,mean=10000,reversion=0.05,volatility=0.001"
+            .parse::<TickerParams>()
+            .unwrap();
+        let mut generator = QuoteGenerator::seeded(universe(&["AAPL"]), 2).with_model(
+            PriceModel::MeanReverting,
+            ModelParams::default(),
+            &[params],
+        );
+        assert_eq!(generator.stocks[0].quote.price, Price::from_cents(20000));
+        let returns = log_returns(&mut generator, 5000);
+        let price = generator.stocks[0].quote.price;
+        let range = Price::from_cents(9800)..Price::from_cents(10200);
+        assert!(range.contains(&price), "price {}", price);
+
+        // Near the mean only the noise is left
+        let (_, deviation) = mean_and_deviation(&returns[1000..]);
+        assert!(
+            (deviation / 0.001 - 1.0).abs() < 0.1,
+            "deviation {}",
+            deviation
+        );
+    }
+
+    #[test]
+    fn test_ticker_params() {
+        let params: TickerParams = "NFLX:drift=0.0001, volatility=0.002".parse().unwrap();
+        let applied = params.apply(ModelParams::default());
+        assert_eq!(applied.drift, 0.0001);
+        assert_eq!(applied.volatility, 0.002);
+        assert_eq!(applied.reversion, ModelParams::default().reversion);
+        assert!("NFLX".parse::<TickerParams>().is_err());
+        assert!("NFLX:speed=2".parse::<TickerParams>().is_err());
+        assert!("NFLX:price=abc".parse::<TickerParams>().is_err());
+        for invalid in [
+            "NFLX:price=0",
+            "NFLX:mean=0.00",
+            "NFLX:volatility=-0.1",
+            "NFLX:reversion=-1",
+            "NFLX:reversion=1.5",
+            "NFLX:drift=NaN",
+            "NFLX:price=1000000000.01",
+        ] {
+            assert!(invalid.parse::<TickerParams>().is_err(), "{}", invalid);
+        }
+        assert!(
+            "NFLX:drift=-0.001,reversion=0,reversion=1"
+                .parse::<TickerParams>()
+                .is_ok()
+        );
+
+        let params: TickerParams = "NFLX:price=18000,mean=170.5".parse().unwrap();
+        assert_eq!(params.price, Some(Price::from_cents(18000)));
+        assert_eq!(params.mean, Some(Price::from_cents(17050)));
+    }
+
+    #[test]
+    fn test_configured_price_on_tick() {
+        let mut configs = universe(&["AAPL"]);
+        configs[0].tick_size = Some(Price::from_cents(25));
+        let params: TickerParams = "AAPL:price=180.10".parse().unwrap();
+        let generator = QuoteGenerator::seeded(configs, 1).with_model(
+            PriceModel::Gbm,
+            ModelParams::default(),
+            &[params],
+        );
+        assert_eq!(generator.stocks[0].quote.price, Price::from_cents(18000));
+        assert_eq!(
+            generator.stocks[0].price,
+            Price::from_cents(18000).units() as f64
+        );
+    }
+
+    #[test]
+    fn test_universe_settings() {
+        let data = r#"
+            [[tickers]]
+            ticker = "AAPL"
+            price = 18000
+            price_range = { min = 17900, max = 18100 }
+            volume = { min = 1000, max = 2000 }
+            weight = 3
+            volatility = 0.01
+            tick_size = 25
+
+            [[tickers]]
+            ticker = "NFLX"
+        "#;
+        let universe = parse_universe(data, UniverseFormat::Toml).unwrap();
+        let mut generator = QuoteGenerator::seeded(universe.tickers, 3).with_model(
+            PriceModel::Gbm,
+            ModelParams::default(),
+            &[],
+        );
+        assert_eq!(generator.stocks[0].quote.price, Price::from_cents(18000));
+        assert_eq!(generator.stocks[0].params.volatility, 0.01);
+
+        let mut counts = HashMap::new();
+        for _ in 0..4000 {
+            let quote = generator.random().unwrap();
+            *counts.entry(quote.ticker.clone()).or_insert(0) += 1;
+            if quote.ticker == "AAPL" {
+                let range = Price::from_cents(17900)..=Price::from_cents(18100);
+                assert!(range.contains(&quote.price));
+                assert_eq!(
+                    quote.price.round_to_tick(Price::from_cents(25)),
+                    quote.price
+                );
+                assert!((1000..=2000).contains(&quote.volume));
+            }
+        }
+        let share = counts["AAPL"] as f64 / 4000.0;
+        assert!((share - 0.75).abs() < 0.03, "share {}", share);
+    }
+
+    #[test]
+    fn test_price_range_ticks() {
+        let mut config = TickerConfig::new("AAPL");
+        config.price = Some("100.25".parse().unwrap());
+        config.price_range = Some(Bounds {
+            min: "100.10".parse().unwrap(),
+            max: "100.40".parse().unwrap(),
+        });
+        config.tick_size = Some(Price::from_cents(25));
+        let mut generator = QuoteGenerator::seeded(vec![config], 5).with_model(
+            PriceModel::Gbm,
+            ModelParams {
+                volatility: 0.01,
+                ..ModelParams::default()
+            },
+            &[],
+        );
+        for _ in 0..200 {
+            assert_eq!(generator.random().unwrap().price.to_string(), "100.25");
+        }
+    }
+
+    #[test]
+    fn test_huge_weights() {
+        let mut configs = universe(&["AAPL", "MSFT"]);
+        for config in &mut configs {
+            config.weight = Some(u64::MAX);
+        }
+        let mut generator = QuoteGenerator::seeded(configs, 5);
+        assert!((0..100).all(|_| generator.random().is_some()));
+    }
+
+    fn correlation(x: &[f64], y: &[f64]) -> f64 {
+        let (mean_x, deviation_x) = mean_and_deviation(x);
+        let (mean_y, deviation_y) = mean_and_deviation(y);
+        let covariance = x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum::<f64>()
+            / (x.len() - 1) as f64;
+        covariance / (deviation_x * deviation_y)
+    }
+
+    #[test]
+    fn test_shock_steps() {
+        let mut configs = universe(&["AAPL", "KO"]);
+        configs[0].sector = Some("tech".to_string());
+        let mut generator = QuoteGenerator::seeded(configs, 3);
+        for _ in 0..5 {
+            generator.advance();
+        }
+        assert_eq!(generator.shock(0).1, 5.0);
+        assert_eq!(generator.shock(1).1, 1.0);
+        generator.advance();
+        assert_eq!(generator.shock(0).1, 1.0);
+    }
+
+    #[test]
+    fn test_sector_correlation() {
+        let data = r#"
+            [[tickers]]
+            ticker = "AAPL"
+            sector = "tech"
+            sector_correlation = 0.9
+
+            [[tickers]]
+            ticker = "MSFT"
+            sector = "tech"
+            sector_correlation = 0.9
+
+            [[tickers]]
+            ticker = "XOM"
+            sector = "energy"
+            sector_correlation = 0.9
+
+            [[tickers]]
+            ticker = "KO"
+
+            [[correlations]]
+            between = ["tech", "energy"]
+            value = 0.5
+        "#;
+        let universe = parse_universe(data, UniverseFormat::Toml).unwrap();
+        let params = ModelParams {
+            volatility: 0.01,
+            price: Some(Price::from_cents(1_000_000)),
+            ..ModelParams::default()
+        };
+        let mut generator = QuoteGenerator::seeded(universe.tickers, 6)
+            .with_model(PriceModel::Gbm, params, &[])
+            .with_correlations(&universe.correlations)
+            .unwrap();
+
+        // Brings every ticker to the current step, so all returns cover the same steps
+        let sync = |generator: &mut QuoteGenerator| -> Vec<f64> {
+            (0..generator.stocks.len())
+                .map(|idx| {
+                    generator.correct_stock(idx);
+                    generator.stocks[idx].price.ln()
+                })
+                .collect()
+        };
+        let mut returns = vec![Vec::new(); 4];
+        let mut previous = sync(&mut generator);
+        for _ in 0..3000 {
+            for _ in 0..10 {
+                generator.random().unwrap();
+            }
+            let prices = sync(&mut generator);
+            for (idx, series) in returns.iter_mut().enumerate() {
+                series.push(prices[idx] - previous[idx]);
+            }
+            previous = prices;
+        }
+
+        let expected = [
+            ((0, 1), 0.81),
+            ((0, 2), 0.405),
+            ((1, 2), 0.405),
+            ((0, 3), 0.0),
+        ];
+        for ((i, j), expected) in expected {
+            let actual = correlation(&returns[i], &returns[j]);
+            assert!(
+                (actual - expected).abs() < 0.06,
+                "correlation of {} and {} is {}, expected {}",
+                i,
+                j,
+                actual,
+                expected
+            );
+        }
+    }
+
+    fn statuses(events: &[SourceEvent]) -> Vec<(Option<String>, TradingPhase)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                SourceEvent::Status(status) => Some((status.ticker.clone(), status.phase)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_session_phases() {
+        let calendar = "00:01,00:02,00:03,00:04".parse().unwrap();
+        let mut generator = QuoteGenerator::seeded(universe(&["AAPL", "NFLX"]), 4)
+            .with_clock(Box::new(FixedClock::new(0, 1000)))
+            .with_session(Some(calendar), TradingHalts::new());
+        let events: Vec<SourceEvent> = (0..400)
+            .map(|_| generator.next_event().unwrap().1)
+            .collect();
+        assert_eq!(
+            statuses(&events),
+            vec![
+                (None, TradingPhase::Closed),
+                (None, TradingPhase::PreOpen),
+                (None, TradingPhase::Open),
+                (None, TradingPhase::ClosingAuction),
+                (None, TradingPhase::Closed),
+            ]
+        );
+
+        let mut phase = TradingPhase::Closed;
+        let mut uncrossed = 0;
+        for event in &events {
+            match event {
+                SourceEvent::Status(status) => phase = status.phase,
+                SourceEvent::Quote(quote) => match phase {
+                    TradingPhase::Closed => panic!("quote while closed"),
+                    TradingPhase::Open if quote.volume > 0 => {}
+                    TradingPhase::PreOpen | TradingPhase::ClosingAuction if quote.volume == 0 => {}
+                    // Auction results are published right before the next phase
+                    _ => uncrossed += 1,
+                },
+                SourceEvent::Trade(_) | SourceEvent::Book(_) => {
+                    assert_eq!(phase, TradingPhase::Open)
+                }
+                SourceEvent::Idle => assert_eq!(phase, TradingPhase::Closed),
+                event => panic!("unexpected {:?}", event),
+            }
+        }
+        assert_eq!(uncrossed, 4);
+    }
+
+    #[test]
+    fn test_halt_and_resume() {
+        let halts = TradingHalts::new();
+        let mut generator = QuoteGenerator::seeded(universe(&["AAPL", "NFLX"]), 5)
+            .with_session(None, halts.clone());
+        let mut next = |count: usize| -> Vec<SourceEvent> {
+            (0..count)
+                .map(|_| generator.next_event().unwrap().1)
+                .collect()
+        };
+        next(10);
+        halts.halt("AAPL");
+        let events = next(100);
+        assert_eq!(
+            statuses(&events),
+            vec![(Some("AAPL".to_string()), TradingPhase::Halted)]
+        );
+        assert!(events.iter().all(|event| match event {
+            SourceEvent::Quote(quote) => quote.ticker == "NFLX",
+            _ => true,
+        }));
+
+        halts.resume("AAPL");
+        let events = next(100);
+        assert_eq!(
+            statuses(&events),
+            vec![(Some("AAPL".to_string()), TradingPhase::Open)]
+        );
+        assert!(events.iter().any(|event| matches!(
+            event,
+            SourceEvent::Quote(quote) if quote.ticker == "AAPL"
+        )));
+    }
+
+    #[test]
+    fn test_scripted_events() {
+        let mut configs = universe(&["AAPL", "MSFT", "TSLA"]);
+        configs[0].sector = Some("tech".to_string());
+        configs[1].sector = Some("tech".to_string());
+        let mut generator =
+            QuoteGenerator::seeded(configs, 6).with_clock(Box::new(FixedClock::new(0, 100)));
+        let events = generator.events();
+        let inject = |event: &str| events.inject(event.parse().unwrap());
+        assert_eq!(
+            inject("NVDA drop 15%"),
+            Err("UNKNOWN_TICKER NVDA".to_string())
+        );
+        assert_eq!(
+            inject("sector:energy drop 15%"),
+            Err("UNKNOWN_SECTOR energy".to_string())
+        );
+        let prices: Vec<f64> = generator
+            .stocks
+            .iter()
+            .map(|s| s.quote.price.to_f64())
+            .collect();
+        inject("sector:tech drop 50%").unwrap();
+        inject("TSLA freeze 2s").unwrap();
+        inject("+1m all spike 10%").unwrap();
+
+        let mut next = |count: usize| -> Vec<StockQuote> {
+            let mut quotes = vec![];
+            while quotes.len() < count {
+                if let SourceEvent::Quote(quote) = generator.next_event().unwrap().1 {
+                    quotes.push(quote);
+                }
+            }
+            quotes
+        };
+        let dropped = next(2);
+        assert_eq!(dropped[0].ticker, "AAPL");
+        assert_eq!(dropped[1].ticker, "MSFT");
+        assert!((dropped[0].price.to_f64() / prices[0] - 0.5).abs() < 0.01);
+        assert!((dropped[1].price.to_f64() / prices[1] - 0.5).abs() < 0.01);
+
+        // The clock advances 200ms per generated quote
+        assert!(next(8).iter().all(|quote| quote.ticker != "TSLA"));
+        assert!(next(50).iter().any(|quote| quote.ticker == "TSLA"));
+        assert_eq!(generator.scheduled.len(), 1);
+    }
+
+    #[test]
+    fn test_delayed_events_checked_when_due() {
+        let mut generator = seeded(8);
+        let events = generator.events();
+        events
+            .inject("+100ms NFLX split 2:1".parse().unwrap())
+            .unwrap();
+        events
+            .inject("+100ms MSFT rename NFX".parse().unwrap())
+            .unwrap();
+        events.inject("NFLX rename NFX".parse().unwrap()).unwrap();
+
+        let actions: Vec<CorporateAction> = (0..300)
+            .filter_map(|_| match generator.next_event().unwrap().1 {
+                SourceEvent::Action(action) => Some(action),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(actions.len(), 1, "{:?}", actions);
+        assert!(generator.scheduled.is_empty());
+        let tickers = generator.tickers();
+        assert!(tickers.contains("MSFT") && tickers.contains("NFX"));
+    }
+
+    #[test]
+    fn test_events_on_time_at_low_rates() {
+        let mut generator = QuoteGenerator::seeded(universe(&["AAPL"]), 3)
+            .with_schedule(Schedule::new(0.5, vec![]))
+            .with_clock(Box::new(FixedClock::new(0, 50)));
+        let price = generator.stocks[0].price;
+        generator
+            .script(vec!["+50ms AAPL drop 50%".parse().unwrap()])
+            .unwrap();
+        assert!(
+            generator
+                .script(vec!["NVDA drop 1%".parse().unwrap()])
+                .is_err()
+        );
+
+        let (delay, event) = generator.next_event().unwrap();
+        assert_eq!(delay, Duration::from_millis(50));
+        assert!(matches!(event, SourceEvent::Idle));
+        let (delay, event) = generator.next_event().unwrap();
+        assert_eq!(delay, Duration::ZERO);
+        let SourceEvent::Quote(quote) = event else {
+            panic!("quote expected, got {:?}", event);
+        };
+        assert!((quote.price.units() as f64 / price - 0.5).abs() < 0.01);
+
+        // The rest of the 2s pause is waited in steps, checking for new events
+        let mut delays = vec![];
+        loop {
+            let (delay, event) = generator.next_event().unwrap();
+            delays.push(delay);
+            if matches!(event, SourceEvent::Quote(_)) {
+                break;
+            }
+        }
+        assert!(delays.iter().all(|delay| *delay <= EVENT_POLL));
+        assert_eq!(delays.iter().sum::<Duration>(), Duration::from_millis(1950));
+    }
+
+    #[test]
+    fn test_injected_events_are_bounded() {
+        let generator = seeded(1);
+        let events = generator.events();
+        for _ in 0..MAX_INJECTED_EVENTS {
+            events.inject("AAPL drop 1%".parse().unwrap()).unwrap();
+        }
+        assert_eq!(
+            events.inject("AAPL drop 1%".parse().unwrap()),
+            Err("TOO_MANY_EVENTS".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resume_last_price() {
+        let mut generator = QuoteGenerator::new(universe(&["AAPL", "NFLX"]));
+        let last = StockQuote::new("AAPL", Price::from_cents(12345), 1000);
+        generator.resume(HashMap::from([(last.ticker.clone(), last)]));
+        assert_eq!(generator.stocks[0].quote.price, Price::from_cents(12345));
+        assert_eq!(generator.stocks[0].quote.volume, 1000);
+    }
 }
@@ -0,0 +1,228 @@
+use crate::generator::{DIVIDER, LOT_SIZE, QuoteGenerator, tick_units};
+use quotes::{
+    BookQuote, Bounds, DepthSnapshot, DepthUpdate, Level, OrderBook, Price, Side, SourceEvent,
+};
+use rand::Rng;
+
+/// Narrowest quoted spread, in basis points of the price
+const MIN_SPREAD: u64 = 2;
+/// Widest quoted spread, in basis points of the price
+const MAX_SPREAD: u64 = 10;
+/// Most lots quoted on one side of the book
+const MAX_LOTS: u64 = 50;
+/// Chance that a depth level below the top keeps its size when the ticker moves
+const KEEP_LEVEL: f64 = 0.7;
+
+impl QuoteGenerator {
+    /// Queues the top of the book of the ticker, followed by its changed depth levels
+    pub(super) fn publish_book(&mut self, idx: usize) {
+        let book = self.book(idx);
+        let update = match self.depth_levels {
+            0 => None,
+            _ => {
+                let bids = self.depth_side(idx, Side::Bid, book.bid, book.bid_size);
+                let asks = self.depth_side(idx, Side::Ask, book.ask, book.ask_size);
+                let depth = &mut self.stocks[idx].depth;
+                let update = DepthUpdate {
+                    ticker: book.ticker.clone(),
+                    sequence: depth.sequence().unwrap_or_default() + 1,
+                    changes: depth.changes_to(&bids, &asks),
+                    timestamp: book.timestamp,
+                };
+                let applied = depth.apply(&update);
+                debug_assert_eq!(applied, Ok(true));
+                Some(update)
+            }
+        };
+        self.pending.push_back(SourceEvent::Book(book));
+        if let Some(update) = update {
+            self.pending.push_back(SourceEvent::Depth(update));
+        }
+    }
+
+    /// `depth_levels` of one side, the best first: the top of the book and the levels
+    /// a tick apart behind it. Levels already in the book mostly keep their size
+    fn depth_side(&mut self, idx: usize, side: Side, top: Price, top_size: u64) -> Vec<Level> {
+        let stock = &self.stocks[idx];
+        let tick = Price::from_units(tick_units(&stock.config));
+        let previous: Vec<Level> = match side {
+            Side::Bid => stock.depth.bids().collect(),
+            Side::Ask => stock.depth.asks().collect(),
+        };
+        let mut levels = vec![Level {
+            price: top,
+            size: top_size,
+        }];
+        let mut price = top;
+        while levels.len() < self.depth_levels {
+            price = match side {
+                Side::Bid => match price.checked_sub(tick) {
+                    Some(price) if !price.is_zero() => price,
+                    _ => break,
+                },
+                Side::Ask => price + tick,
+            };
+            let kept = previous
+                .iter()
+                .find(|level| level.price == price)
+                .filter(|_| self.rng.random_bool(KEEP_LEVEL));
+            let size = match kept {
+                Some(level) => level.size,
+                None => {
+                    self.random_in(Bounds {
+                        min: 1,
+                        max: MAX_LOTS,
+                    }) * LOT_SIZE
+                }
+            };
+            levels.push(Level { price, size });
+        }
+        levels
+    }
+
+    /// Queues the full depth of every ticker once `snapshot_interval` has passed
+    pub(super) fn snapshot_depth(&mut self, now: u64) {
+        let interval = self.snapshot_interval.as_millis() as u64;
+        if self.depth_levels == 0 || self.last_snapshot.is_some_and(|last| now < last + interval) {
+            return;
+        }
+        self.last_snapshot = Some(now);
+        for stock in &self.stocks {
+            self.pending
+                .push_back(SourceEvent::DepthSnapshot(stock.depth.snapshot(now)));
+        }
+    }
+
+    /// Top of the book around the unrounded price of the ticker. The spread is a random
+    /// number of basis points but at least one tick, the bid and ask bracket the last trade
+    fn book(&mut self, idx: usize) -> BookQuote {
+        let spread = self.random_in(Bounds {
+            min: MIN_SPREAD,
+            max: MAX_SPREAD,
+        });
+        let lots = Bounds {
+            min: 1,
+            max: MAX_LOTS,
+        };
+        let bid_size = self.random_in(lots) * LOT_SIZE;
+        let ask_size = self.random_in(lots) * LOT_SIZE;
+        let stock = &self.stocks[idx];
+        let tick = tick_units(&stock.config);
+        let half_spread = stock.price * spread as f64 / DIVIDER as f64 / 2.0;
+        let half_spread = half_spread.max(tick as f64 / 2.0);
+        let bid = ((stock.price - half_spread) / tick as f64).floor().max(1.0) as u64;
+        let ask = ((stock.price + half_spread) / tick as f64).ceil() as u64;
+        BookQuote::new(
+            &stock.quote.ticker,
+            Price::from_units(bid * tick),
+            bid_size,
+            Price::from_units(ask.max(bid + 1) * tick),
+            ask_size,
+            stock.quote.timestamp,
+        )
+    }
+}
+
+/// Book of a ticker before any depth was published
+pub(super) fn empty_depth(ticker: &str) -> OrderBook {
+    let mut depth = OrderBook::new(ticker);
+    let _ = depth.apply_snapshot(&DepthSnapshot {
+        ticker: ticker.to_string(),
+        sequence: 0,
+        bids: vec![],
+        asks: vec![],
+        timestamp: 0,
+    });
+    depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::test_helpers::{non_trade_events, seeded, universe};
+    use crate::generator::{ModelParams, PriceModel};
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_book_quotes() {
+        let mut configs = universe(&["AAPL", "NFLX"]);
+        configs[0].tick_size = Some(Price::from_cents(25));
+        let mut generator = QuoteGenerator::seeded(configs, 9).with_model(
+            PriceModel::Uniform,
+            ModelParams::default(),
+            &[],
+        );
+        let mut last = None;
+        let mut books = 0;
+        for event in non_trade_events(&mut generator).take(2000) {
+            match event {
+                SourceEvent::Quote(quote) => last = Some(quote),
+                SourceEvent::Book(book) => {
+                    let trade = last.take().expect("book quote follows its trade");
+                    assert_eq!(book.ticker, trade.ticker);
+                    assert_eq!(book.timestamp, trade.timestamp);
+                    assert!(book.bid < book.ask, "{:?}", book);
+                    assert!((book.bid..=book.ask).contains(&trade.price), "{:?}", book);
+                    let spread = book.spread().to_f64() / book.mid().to_f64();
+                    assert!(spread <= 0.002 || book.spread() <= Price::from_cents(25));
+                    assert!(book.bid_size > 0 && book.bid_size.is_multiple_of(LOT_SIZE));
+                    assert!(book.ask_size > 0 && book.ask_size.is_multiple_of(LOT_SIZE));
+                    if book.ticker == "AAPL" {
+                        let tick = Price::from_cents(25);
+                        assert_eq!(book.bid.round_to_tick(tick), book.bid);
+                        assert_eq!(book.ask.round_to_tick(tick), book.ask);
+                    }
+                    books += 1;
+                }
+                event => panic!("unexpected {:?}", event),
+            }
+        }
+        assert_eq!(books, 1000);
+    }
+
+    #[test]
+    fn test_depth_follows_book() {
+        let mut generator = seeded(10).with_depth(3, Duration::from_secs(1));
+        let events = generator.events();
+        let mut books: HashMap<String, OrderBook> = HashMap::new();
+        let (mut snapshots, mut updates) = (0, 0);
+        let mut top = None;
+        for (step, event) in non_trade_events(&mut generator).take(3000).enumerate() {
+            if step == 1500 {
+                events.inject("NFLX rename NFX".parse().unwrap()).unwrap();
+            }
+            match event {
+                SourceEvent::Book(book) => top = Some(book),
+                SourceEvent::DepthSnapshot(snapshot) => {
+                    let book = books
+                        .entry(snapshot.ticker.clone())
+                        .or_insert_with(|| OrderBook::new(&snapshot.ticker));
+                    book.apply_snapshot(&snapshot).unwrap();
+                    snapshots += 1;
+                }
+                SourceEvent::Depth(update) => {
+                    let top = top.take().expect("depth follows the top of the book");
+                    assert_eq!(update.ticker, top.ticker);
+                    let book = books
+                        .get_mut(&update.ticker)
+                        .expect("snapshot precedes updates");
+                    assert_eq!(book.apply(&update), Ok(true));
+                    assert_eq!(book.best_bid().unwrap().price, top.bid);
+                    assert_eq!(book.best_ask().unwrap().price, top.ask);
+                    assert_eq!(book.best_ask().unwrap().size, top.ask_size);
+                    assert_eq!(book.bids().count(), 3);
+                    assert_eq!(book.asks().count(), 3);
+                    updates += 1;
+                }
+                SourceEvent::Quote(_) | SourceEvent::Action(_) => {}
+                event => panic!("unexpected {:?}", event),
+            }
+        }
+        // About 1000 quotes take 20 seconds of the clock: 3 snapshots a second, 1 on the rename
+        assert!((58..=64).contains(&snapshots), "snapshots {}", snapshots);
+        assert!(updates > 900, "updates {}", updates);
+        assert!(books["NFX"].sequence() > Some(0));
+        assert!(books["NFX"].sequence() > books["NFLX"].sequence());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::generator::{DEFAULT_VOLUME, QuoteGenerator, Ticker};
+use quotes::{Bounds, CorporateAction, OrderBook, Price, SourceEvent};
+use std::mem;
+
+impl Ticker {
+    /// Multiplies prices by `factor` of a split and volumes by its inverse
+    fn split(&mut self, factor: f64, timestamp: u64) {
+        let scale = |value: u64, factor: f64| ((value as f64 * factor).round() as u64).max(1);
+        let scale_price = |price: Price| Price::from_units(scale(price.units(), factor));
+        self.config.price_range = self.config.price_range.map(|range| Bounds {
+            min: scale_price(range.min),
+            max: scale_price(range.max),
+        });
+        let volume = self.config.volume.unwrap_or(DEFAULT_VOLUME);
+        self.config.volume = Some(Bounds {
+            min: scale(volume.min, 1.0 / factor),
+            max: scale(volume.max, 1.0 / factor),
+        });
+        self.params.mean = self.params.mean.map(scale_price);
+        self.mean *= factor;
+        self.activity = scale(self.activity, 1.0 / factor);
+        let volume = scale(self.quote.volume, 1.0 / factor);
+        self.set_price(self.price * factor, volume, timestamp);
+    }
+}
+
+impl QuoteGenerator {
+    /// Turns every `old_shares` shares of the ticker into `new_shares` and queues the split
+    pub(super) fn split_ticker(&mut self, idx: usize, new_shares: u32, old_shares: u32, now: u64) {
+        let stock = &mut self.stocks[idx];
+        let action = CorporateAction::split(&stock.quote.ticker, new_shares, old_shares, now);
+        stock.split(action.price_factor(), now);
+        self.pending.push_back(SourceEvent::Action(action));
+    }
+
+    /// Publishes the ticker as `symbol` from now on, its halt, freeze and depth move along
+    pub(super) fn rename_ticker(&mut self, idx: usize, symbol: &str, now: u64) {
+        let stock = &mut self.stocks[idx];
+        let ticker = mem::replace(&mut stock.quote.ticker, symbol.to_string());
+        stock.config.ticker = symbol.to_string();
+        self.tickers.rename(&ticker, symbol);
+        if self.halts.halted().contains(&ticker) {
+            self.halts.resume(&ticker);
+            self.halts.halt(symbol);
+        }
+        if self.halted.remove(&ticker) {
+            self.halted.insert(symbol.to_string());
+        }
+        if let Some(until) = self.frozen.remove(&ticker) {
+            self.frozen.insert(symbol.to_string(), until);
+        }
+        self.pending
+            .push_back(SourceEvent::Action(CorporateAction::rename(
+                &ticker, symbol, now,
+            )));
+        let mut snapshot = stock.depth.snapshot(now);
+        snapshot.ticker = symbol.to_string();
+        stock.depth = OrderBook::new(symbol);
+        let _ = stock.depth.apply_snapshot(&snapshot);
+        if self.depth_levels > 0 {
+            self.pending.push_back(SourceEvent::DepthSnapshot(snapshot));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::quantize;
+    use crate::generator::test_helpers::seeded;
+    use quotes::QuoteSource;
+
+    #[test]
+    fn test_corporate_actions() {
+        let mut generator = seeded(8);
+        let events = generator.events();
+        let price = generator.stocks[0].price;
+        events.inject("AAPL split 4:1".parse().unwrap()).unwrap();
+        events.inject("NFLX rename NFX".parse().unwrap()).unwrap();
+        assert_eq!(
+            events.inject("MSFT rename AAPL".parse().unwrap()),
+            Err("TICKER_EXISTS AAPL".to_string())
+        );
+
+        let published: Vec<SourceEvent> = (0..4)
+            .map(|_| generator.next_event().unwrap().1)
+            .filter(|event| !matches!(event, SourceEvent::Trade(_) | SourceEvent::Book(_)))
+            .collect();
+        let SourceEvent::Action(split) = &published[0] else {
+            panic!("split expected, got {:?}", published[0]);
+        };
+        assert_eq!(split.price_factor(), 0.25);
+        let SourceEvent::Quote(quote) = &published[1] else {
+            panic!("quote expected, got {:?}", published[1]);
+        };
+        assert_eq!(
+            quote.price,
+            quantize(&generator.stocks[0].config, price / 4.0)
+        );
+        let SourceEvent::Action(rename) = &published[2] else {
+            panic!("rename expected, got {:?}", published[2]);
+        };
+        assert_eq!(
+            rename,
+            &CorporateAction::rename("NFLX", "NFX", rename.timestamp)
+        );
+
+        let tickers = generator.tickers();
+        assert!(tickers.contains("NFX") && !tickers.contains("NFLX"));
+        assert!(events.inject("NFLX drop 1%".parse().unwrap()).is_err());
+        let renamed = (0..100).any(|_| {
+            matches!(generator.next_event().unwrap().1, SourceEvent::Quote(q) if q.ticker == "NFX")
+        });
+        assert!(renamed);
+    }
+}
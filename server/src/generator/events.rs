@@ -0,0 +1,282 @@
+use crate::generator::schedule::parse_duration;
+use crate::tickers::Tickers;
+use crossbeam::channel::{Sender, TrySendError};
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use std::{fs, io};
+
+const SECTOR_PREFIX: &str = "sector:";
+const ALL_TICKERS: &str = "all";
+/// 100% in basis points
+const BASIS_POINTS: i64 = 10000;
+
+/// Tickers a scripted event applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Target {
+    Ticker(String),
+    /// Every ticker of a sector, written `sector:tech`
+    Sector(String),
+    /// Every ticker, written `all`
+    All,
+}
+
+/// What a scripted event does to its tickers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// Moves the price at once by this many basis points: a crash, a spike or a gap
+    Move(i64),
+    /// Publishes no quotes for a while
+    Freeze(Duration),
+    /// Turns every `old_shares` shares into `new_shares`, scaling the price accordingly
+    Split { new_shares: u32, old_shares: u32 },
+    /// Publishes the ticker under a new symbol
+    Rename(String),
+}
+
+/// Market event injected into the generator, e.g. `+30s NVDA drop 15%`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ScriptedEvent {
+    /// Delay from the scenario start or from the command
+    pub(crate) delay: Duration,
+    pub(crate) target: Target,
+    pub(crate) action: Action,
+}
+
+impl FromStr for ScriptedEvent {
+    type Err = String;
+
+    /// Parses `[+DELAY] TARGET ACTION VALUE` with actions `drop|spike|gap PERCENT%`,
+    /// `freeze DURATION`, `split NEW:OLD` and `rename SYMBOL`, e.g. `+30s NVDA drop 15%`,
+    /// `sector:tech gap +3%`, `+1m TSLA freeze 10s`, `AAPL split 4:1`, `FB rename META`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid event '{}', expected e.g. '+30s NVDA drop 15%'",
+                s.trim()
+            )
+        };
+        let mut parts = s.split_whitespace().peekable();
+        let delay = match parts.next_if(|part| part.starts_with('+')) {
+            Some(delay) => parse_duration(&delay[1..]).ok_or_else(invalid)?,
+            None => Duration::ZERO,
+        };
+        let (Some(target), Some(verb), Some(value), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let target = match target {
+            ALL_TICKERS => Target::All,
+            target => match target.strip_prefix(SECTOR_PREFIX) {
+                Some(sector) => Target::Sector(sector.to_string()),
+                None => Target::Ticker(target.to_string()),
+            },
+        };
+        let action = match verb {
+            "drop" => Action::Move(-parse_percent(value).ok_or_else(invalid)?.abs()),
+            "spike" => Action::Move(parse_percent(value).ok_or_else(invalid)?.abs()),
+            "gap" => Action::Move(parse_percent(value).ok_or_else(invalid)?),
+            "freeze" => Action::Freeze(parse_duration(value).ok_or_else(invalid)?),
+            "split" => {
+                let (new_shares, old_shares) = parse_ratio(value).ok_or_else(invalid)?;
+                Action::Split {
+                    new_shares,
+                    old_shares,
+                }
+            }
+            "rename" if value != ALL_TICKERS && !value.starts_with(SECTOR_PREFIX) => {
+                Action::Rename(value.to_string())
+            }
+            _ => return Err(invalid()),
+        };
+        if matches!(action, Action::Split { .. } | Action::Rename(_))
+            && !matches!(target, Target::Ticker(_))
+        {
+            return Err(format!("event '{}' must target a single ticker", s.trim()));
+        }
+        if let Action::Move(change) = action
+            && change <= -BASIS_POINTS
+        {
+            return Err(format!("event '{}' must not drop prices by 100%", s.trim()));
+        }
+        Ok(ScriptedEvent {
+            delay,
+            target,
+            action,
+        })
+    }
+}
+
+/// `15%`, `+2.5%` or `-3%` as basis points
+fn parse_percent(s: &str) -> Option<i64> {
+    let percent: f64 = s.strip_suffix('%')?.parse().ok()?;
+    percent
+        .is_finite()
+        .then(|| (percent * (BASIS_POINTS / 100) as f64).round() as i64)
+}
+
+/// `4:1` as positive new and old share counts
+fn parse_ratio(s: &str) -> Option<(u32, u32)> {
+    let (new_shares, old_shares) = s.split_once(':')?;
+    let (new_shares, old_shares) = (new_shares.parse().ok()?, old_shares.parse().ok()?);
+    (new_shares > 0 && old_shares > 0).then_some((new_shares, old_shares))
+}
+
+impl fmt::Display for ScriptedEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if !self.delay.is_zero() {
+            write!(f, "+{} ", DisplayDuration(self.delay))?;
+        }
+        match &self.target {
+            Target::Ticker(ticker) => write!(f, "{} ", ticker)?,
+            Target::Sector(sector) => write!(f, "{}{} ", SECTOR_PREFIX, sector)?,
+            Target::All => write!(f, "{} ", ALL_TICKERS)?,
+        }
+        let percent = |change: i64| change.abs() as f64 / (BASIS_POINTS / 100) as f64;
+        match &self.action {
+            Action::Move(change) if *change < 0 => write!(f, "drop {}%", percent(*change)),
+            Action::Move(change) => write!(f, "spike {}%", percent(*change)),
+            Action::Freeze(duration) => write!(f, "freeze {}", DisplayDuration(*duration)),
+            Action::Split {
+                new_shares,
+                old_shares,
+            } => write!(f, "split {}:{}", new_shares, old_shares),
+            Action::Rename(symbol) => write!(f, "rename {}", symbol),
+        }
+    }
+}
+
+/// Duration in the `5s`/`500ms` form accepted by `parse_duration`
+struct DisplayDuration(Duration);
+
+impl fmt::Display for DisplayDuration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let millis = self.0.as_millis();
+        if millis.is_multiple_of(1000) {
+            write!(f, "{}s", millis / 1000)
+        } else {
+            write!(f, "{}ms", millis)
+        }
+    }
+}
+
+/// Reads a scenario: one event per line, blank lines and `#` comments are skipped
+pub(crate) fn load_scenario(path: &Path) -> io::Result<Vec<ScriptedEvent>> {
+    let data = fs::read_to_string(path)?;
+    let mut events = Vec::new();
+    for (number, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let event = line.parse().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), number + 1, e),
+            )
+        })?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// Hands scripted events over to the running generator
+#[derive(Clone)]
+pub(crate) struct MarketEvents {
+    pub(super) tx: Sender<ScriptedEvent>,
+    pub(super) tickers: Tickers,
+    pub(super) sectors: HashSet<String>,
+}
+
+impl ScriptedEvent {
+    /// Fails if the event targets a ticker or sector the generator does not have, or
+    /// renames a ticker to a symbol already in use
+    pub(crate) fn check(
+        &self,
+        tickers: &Tickers,
+        has_sector: impl Fn(&str) -> bool,
+    ) -> Result<(), String> {
+        if let Action::Rename(symbol) = &self.action
+            && tickers.contains(symbol)
+        {
+            return Err(format!("TICKER_EXISTS {}", symbol));
+        }
+        match &self.target {
+            Target::Ticker(ticker) if !tickers.contains(ticker) => {
+                Err(format!("UNKNOWN_TICKER {}", ticker))
+            }
+            Target::Sector(sector) if !has_sector(sector) => {
+                Err(format!("UNKNOWN_SECTOR {}", sector))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl MarketEvents {
+    /// Schedules `event` unless it fails [`ScriptedEvent::check`] or too many events wait
+    /// for the generator. The generator checks it again when it is due, as the tickers may
+    /// have been renamed meanwhile
+    pub(crate) fn inject(&self, event: ScriptedEvent) -> Result<(), String> {
+        event.check(&self.tickers, |sector| self.sectors.contains(sector))?;
+        self.tx.try_send(event).map_err(|e| match e {
+            TrySendError::Full(_) => "TOO_MANY_EVENTS".to_string(),
+            TrySendError::Disconnected(_) => "Quote source has stopped".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events() {
+        let event: ScriptedEvent = "+30s NVDA drop 15%".parse().unwrap();
+        assert_eq!(
+            event,
+            ScriptedEvent {
+                delay: Duration::from_secs(30),
+                target: Target::Ticker("NVDA".to_string()),
+                action: Action::Move(-1500),
+            }
+        );
+        let event: ScriptedEvent = "sector:tech gap +2.5%".parse().unwrap();
+        assert_eq!(event.target, Target::Sector("tech".to_string()));
+        assert_eq!(event.action, Action::Move(250));
+        assert_eq!(event.to_string(), "sector:tech spike 2.5%");
+        let event: ScriptedEvent = "+1500ms all freeze 10s".parse().unwrap();
+        assert_eq!(event.action, Action::Freeze(Duration::from_secs(10)));
+        assert_eq!(event.to_string(), "+1500ms all freeze 10s");
+
+        for event in ["+30s NVDA drop 15%", "all gap -3%", "TSLA freeze 500ms"] {
+            let event: ScriptedEvent = event.parse().unwrap();
+            assert_eq!(event.to_string().parse::<ScriptedEvent>().unwrap(), event);
+        }
+
+        assert!("NVDA drop".parse::<ScriptedEvent>().is_err());
+        assert!("NVDA drop 15".parse::<ScriptedEvent>().is_err());
+        assert!("NVDA drop 100%".parse::<ScriptedEvent>().is_err());
+        assert!("30s NVDA drop 15%".parse::<ScriptedEvent>().is_err());
+        assert!("NVDA sell 15%".parse::<ScriptedEvent>().is_err());
+
+        let event: ScriptedEvent = "+1m AAPL split 1:10".parse().unwrap();
+        assert_eq!(
+            event.action,
+            Action::Split {
+                new_shares: 1,
+                old_shares: 10
+            }
+        );
+        for event in ["+60s AAPL split 1:10", "FB rename META"] {
+            assert_eq!(event.parse::<ScriptedEvent>().unwrap().to_string(), event);
+        }
+        assert!("AAPL split 4:0".parse::<ScriptedEvent>().is_err());
+        assert!("sector:tech split 2:1".parse::<ScriptedEvent>().is_err());
+        assert!("all rename META".parse::<ScriptedEvent>().is_err());
+    }
+}
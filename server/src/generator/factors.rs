@@ -0,0 +1,124 @@
+use quotes::SectorCorrelation;
+use std::collections::HashMap;
+
+/// Pivots below this are treated as zero, so perfectly correlated sectors are accepted
+const EPSILON: f64 = 1e-12;
+
+/// Shared shocks of sectors, correlated according to the sector correlation matrix
+pub(crate) struct Factors {
+    /// Lower triangular Cholesky factor of the correlation matrix
+    cholesky: Vec<Vec<f64>>,
+    /// Cumulative shock of each sector since the start
+    levels: Vec<f64>,
+}
+
+impl Factors {
+    /// Factors of `sectors`, pairs missing from `correlations` are uncorrelated
+    pub(crate) fn new(
+        sectors: &[String],
+        correlations: &[SectorCorrelation],
+    ) -> Result<Self, String> {
+        let index: HashMap<&str, usize> = sectors
+            .iter()
+            .enumerate()
+            .map(|(i, sector)| (sector.as_str(), i))
+            .collect();
+        let mut matrix = vec![vec![0.0; sectors.len()]; sectors.len()];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        for correlation in correlations {
+            let [first, second] = &correlation.between;
+            let (Some(&i), Some(&j)) = (index.get(first.as_str()), index.get(second.as_str()))
+            else {
+                continue;
+            };
+            matrix[i][j] = correlation.value;
+            matrix[j][i] = correlation.value;
+        }
+        let cholesky = cholesky(&matrix)
+            .ok_or_else(|| "sector correlation matrix is not positive semi-definite".to_string())?;
+        Ok(Self {
+            cholesky,
+            levels: vec![0.0; sectors.len()],
+        })
+    }
+
+    /// Uncorrelated factors of `count` sectors
+    pub(crate) fn independent(count: usize) -> Self {
+        let cholesky = (0..count)
+            .map(|i| (0..count).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+            .collect();
+        Self {
+            cholesky,
+            levels: vec![0.0; count],
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Adds one step of correlated shocks built from independent standard normals
+    pub(crate) fn advance(&mut self, mut normal: impl FnMut() -> f64) {
+        let independent: Vec<f64> = self.levels.iter().map(|_| normal()).collect();
+        for (level, row) in self.levels.iter_mut().zip(&self.cholesky) {
+            *level += row
+                .iter()
+                .zip(&independent)
+                .map(|(l, z)| l * z)
+                .sum::<f64>();
+        }
+    }
+
+    pub(crate) fn level(&self, sector: usize) -> f64 {
+        self.levels[sector]
+    }
+}
+
+/// `L` with `L * L^T = matrix`, `None` unless the matrix is positive semi-definite
+fn cholesky(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut lower = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+            if i == j {
+                let pivot = matrix[i][i] - sum;
+                if pivot < -EPSILON {
+                    return None;
+                }
+                lower[i][i] = pivot.max(0.0).sqrt();
+            } else if lower[j][j] > EPSILON {
+                lower[i][j] = (matrix[i][j] - sum) / lower[j][j];
+            } else if (matrix[i][j] - sum).abs() > EPSILON {
+                return None;
+            }
+        }
+    }
+    Some(lower)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn correlation(first: &str, second: &str, value: f64) -> SectorCorrelation {
+        SectorCorrelation {
+            between: [first.to_string(), second.to_string()],
+            value,
+        }
+    }
+
+    #[test]
+    fn test_invalid_matrix_rejected() {
+        let sectors = ["a", "b", "c"].map(String::from);
+        let correlations = [
+            correlation("a", "b", 0.9),
+            correlation("b", "c", 0.9),
+            correlation("a", "c", -0.9),
+        ];
+        assert!(Factors::new(&sectors, &correlations).is_err());
+        assert!(Factors::new(&sectors, &[correlation("a", "b", 1.0)]).is_ok());
+    }
+}
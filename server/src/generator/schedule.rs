@@ -0,0 +1,150 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// A higher rate held for `duration` at the start of every `period`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Burst {
+    rate: f64,
+    duration: Duration,
+    period: Duration,
+}
+
+impl FromStr for Burst {
+    type Err = String;
+
+    /// Parses `RATE:DURATION:PERIOD`, e.g. `10000:5s:1m`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid burst '{}', expected e.g. 10000:5s:1m", s);
+        let mut parts = s.split(':');
+        let (Some(rate), Some(duration), Some(period), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let rate = parse_rate(rate)?;
+        let duration = parse_duration(duration).ok_or_else(invalid)?;
+        let period = parse_duration(period).ok_or_else(invalid)?;
+        if period.is_zero() || duration > period {
+            return Err(format!(
+                "burst '{}' must not last longer than its period",
+                s
+            ));
+        }
+        Ok(Burst {
+            rate,
+            duration,
+            period,
+        })
+    }
+}
+
+impl fmt::Display for Burst {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} quotes/s for {:?} every {:?}",
+            self.rate, self.duration, self.period
+        )
+    }
+}
+
+/// Quotes per second, must be positive
+pub(crate) fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("invalid rate '{}', expected quotes per second", s)),
+    }
+}
+
+/// `500ms`, `5s` or `1m`
+pub(crate) fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (value, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit())?);
+    let value: u64 = value.parse().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(value)),
+        "s" => Some(Duration::from_secs(value)),
+        "m" => Some(Duration::from_secs(value * 60)),
+        _ => None,
+    }
+}
+
+/// Publication rate over time: a base rate with periodic bursts
+pub(crate) struct Schedule {
+    rate: f64,
+    bursts: Vec<Burst>,
+    started: Option<Instant>,
+}
+
+impl Schedule {
+    pub(crate) fn new(rate: f64, bursts: Vec<Burst>) -> Self {
+        Self {
+            rate,
+            bursts,
+            started: None,
+        }
+    }
+
+    /// Rate at `elapsed` since the start, the highest of the active bursts and the base rate
+    fn rate_at(&self, elapsed: Duration) -> f64 {
+        self.bursts
+            .iter()
+            .filter(|burst| {
+                elapsed.as_nanos() % burst.period.as_nanos() < burst.duration.as_nanos()
+            })
+            .map(|burst| burst.rate)
+            .fold(self.rate, f64::max)
+    }
+
+    /// Pause before the next quote, the schedule starts with the first call
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        let elapsed = self.started.get_or_insert_with(Instant::now).elapsed();
+        Duration::from_secs_f64(1.0 / self.rate_at(elapsed))
+    }
+}
+
+impl Default for Schedule {
+    /// One quote every 10 ms
+    fn default() -> Self {
+        Self::new(100.0, vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_burst() {
+        let burst: Burst = "10000:5s:1m".parse().unwrap();
+        assert_eq!(
+            burst,
+            Burst {
+                rate: 10000.0,
+                duration: Duration::from_secs(5),
+                period: Duration::from_secs(60),
+            }
+        );
+        assert!("10000:5s".parse::<Burst>().is_err());
+        assert!("0:5s:1m".parse::<Burst>().is_err());
+        assert!("100:2m:1m".parse::<Burst>().is_err());
+        assert!("100:5x:1m".parse::<Burst>().is_err());
+    }
+
+    #[test]
+    fn test_rate_at() {
+        let schedule = Schedule::new(
+            100.0,
+            vec![
+                "10000:5s:1m".parse().unwrap(),
+                "500:500ms:2s".parse().unwrap(),
+            ],
+        );
+        assert_eq!(schedule.rate_at(Duration::from_secs(1)), 10000.0);
+        assert_eq!(schedule.rate_at(Duration::from_secs(7)), 100.0);
+        assert_eq!(schedule.rate_at(Duration::from_millis(8200)), 500.0);
+        assert_eq!(schedule.rate_at(Duration::from_secs(61)), 10000.0);
+    }
+}
@@ -0,0 +1,116 @@
+use parking_lot::RwLock;
+use quotes::TradingPhase;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+const MINUTE_MILLIS: u64 = 60 * 1000;
+const DAY_MILLIS: u64 = 24 * 60 * MINUTE_MILLIS;
+
+/// Daily trading hours, UTC milliseconds since midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Calendar {
+    pre_open: u64,
+    open: u64,
+    closing_auction: u64,
+    close: u64,
+}
+
+impl Calendar {
+    /// Phase of the market at `timestamp` (unix millis)
+    pub(crate) fn phase_at(&self, timestamp: u64) -> TradingPhase {
+        let time = timestamp % DAY_MILLIS;
+        if time < self.pre_open || time >= self.close {
+            TradingPhase::Closed
+        } else if time < self.open {
+            TradingPhase::PreOpen
+        } else if time < self.closing_auction {
+            TradingPhase::Open
+        } else {
+            TradingPhase::ClosingAuction
+        }
+    }
+}
+
+impl FromStr for Calendar {
+    type Err = String;
+
+    /// Parses `PRE_OPEN,OPEN,CLOSING_AUCTION,CLOSE` times of day, e.g. `08:00,09:30,15:50,16:00`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let times = s
+            .split(',')
+            .map(parse_time)
+            .collect::<Option<Vec<u64>>>()
+            .ok_or_else(|| {
+                format!(
+                    "invalid calendar '{}', expected e.g. 08:00,09:30,15:50,16:00",
+                    s
+                )
+            })?;
+        let [pre_open, open, closing_auction, close] = times[..] else {
+            return Err(format!("calendar '{}' must list 4 times", s));
+        };
+        if !(pre_open <= open && open <= closing_auction && closing_auction <= close) {
+            return Err(format!("calendar '{}' times must not decrease", s));
+        }
+        Ok(Calendar {
+            pre_open,
+            open,
+            closing_auction,
+            close,
+        })
+    }
+}
+
+/// `HH:MM` as milliseconds since midnight, `24:00` included
+fn parse_time(s: &str) -> Option<u64> {
+    let (hours, minutes) = s.trim().split_once(':')?;
+    let (hours, minutes): (u64, u64) = (hours.parse().ok()?, minutes.parse().ok()?);
+    let time = (hours * 60 + minutes) * MINUTE_MILLIS;
+    (minutes < 60 && time <= DAY_MILLIS).then_some(time)
+}
+
+/// Tickers halted by control commands, shared with the generator
+#[derive(Clone, Default)]
+pub(crate) struct TradingHalts {
+    halted: Arc<RwLock<HashSet<String>>>,
+}
+
+impl TradingHalts {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn halt(&self, ticker: &str) {
+        self.halted.write().insert(ticker.to_string());
+    }
+
+    pub(crate) fn resume(&self, ticker: &str) {
+        self.halted.write().remove(ticker);
+    }
+
+    pub(crate) fn halted(&self) -> HashSet<String> {
+        self.halted.read().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calendar_phases() {
+        let calendar: Calendar = "08:00,09:30,15:50,16:00".parse().unwrap();
+        let at = |hours: u64, minutes: u64| 3 * DAY_MILLIS + (hours * 60 + minutes) * MINUTE_MILLIS;
+        assert_eq!(calendar.phase_at(at(7, 59)), TradingPhase::Closed);
+        assert_eq!(calendar.phase_at(at(8, 0)), TradingPhase::PreOpen);
+        assert_eq!(calendar.phase_at(at(12, 0)), TradingPhase::Open);
+        assert_eq!(calendar.phase_at(at(15, 55)), TradingPhase::ClosingAuction);
+        assert_eq!(calendar.phase_at(at(16, 0)), TradingPhase::Closed);
+
+        assert!("08:00,09:30,15:50".parse::<Calendar>().is_err());
+        assert!("09:30,08:00,15:50,16:00".parse::<Calendar>().is_err());
+        assert!("08:00,09:61,15:50,16:00".parse::<Calendar>().is_err());
+        assert!("00:00,00:00,24:00,24:00".parse::<Calendar>().is_ok());
+    }
+}
@@ -0,0 +1,22 @@
+use crate::generator::QuoteGenerator;
+use quotes::{FixedClock, QuoteSource, SourceEvent, TickerConfig};
+use std::iter;
+
+/// Universe of `tickers` with every setting left to the generator
+pub(crate) fn universe(tickers: &[&str]) -> Vec<TickerConfig> {
+    tickers.iter().map(|t| TickerConfig::new(t)).collect()
+}
+
+/// Generator of three tickers with a clock starting at 1000 seconds and ticking 10ms per reading
+pub(crate) fn seeded(seed: u64) -> QuoteGenerator {
+    QuoteGenerator::seeded(universe(&["AAPL", "MSFT", "NFLX"]), seed)
+        .with_clock(Box::new(FixedClock::new(1_000_000, 10)))
+}
+
+/// Events of `generator` without its trade prints
+pub(crate) fn non_trade_events(
+    generator: &mut QuoteGenerator,
+) -> impl Iterator<Item = SourceEvent> + '_ {
+    iter::repeat_with(|| generator.next_event().unwrap().1)
+        .filter(|event| !matches!(event, SourceEvent::Trade(_)))
+}
@@ -0,0 +1,110 @@
+use crate::generator::{LOT_SIZE, QuoteGenerator};
+use quotes::{Bounds, Side, SourceEvent, Trade};
+use rand::Rng;
+use std::mem;
+
+/// Most trades printing the volume of one quote
+const MAX_PRINTS: usize = 4;
+/// Most lots in one trade, the last trade of a quote takes the rest of its volume
+const MAX_PRINT_LOTS: u64 = 5;
+
+impl QuoteGenerator {
+    /// Queues trades of the ticker's last move, all at its price, and adds them to the running
+    /// volume of its quote. Buyers lift the ask when the price rises and sellers hit the bid
+    /// when it falls
+    pub(super) fn publish_trades(&mut self, idx: usize) {
+        let mut volume = mem::take(&mut self.stocks[idx].unprinted);
+        if volume == 0 {
+            return;
+        }
+        let aggressor = match self.stocks[idx].aggressor {
+            Some(side) => side,
+            None if self.rng.random_bool(0.5) => Side::Bid,
+            None => Side::Ask,
+        };
+        let mut prints = 0;
+        while volume > 0 {
+            prints += 1;
+            let size = match prints {
+                MAX_PRINTS => volume,
+                _ => {
+                    let lots = self.random_in(Bounds {
+                        min: 1,
+                        max: MAX_PRINT_LOTS,
+                    });
+                    (lots * LOT_SIZE).min(volume)
+                }
+            };
+            volume -= size;
+            self.trade_id += 1;
+            let quote = &mut self.stocks[idx].quote;
+            quote.volume = quote.volume.saturating_add(size);
+            let trade = Trade::new(
+                &quote.ticker,
+                self.trade_id,
+                quote.price,
+                size,
+                aggressor,
+                quote.timestamp,
+            );
+            self.pending.push_back(SourceEvent::Trade(trade));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::test_helpers::seeded;
+    use quotes::{Price, QuoteSource, StockQuote};
+    use std::cmp::Ordering;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_trades_add_up_to_volume_growth() {
+        let mut generator = seeded(11);
+        let mut last: HashMap<String, StockQuote> = generator
+            .stocks
+            .iter()
+            .map(|stock| (stock.quote.ticker.clone(), stock.quote.clone()))
+            .collect();
+        let (mut growth, mut printed) = (0, 0);
+        let mut trade_id = 0;
+        // The quote being printed and the price before it
+        let mut current: Option<(StockQuote, Price)> = None;
+        for _ in 0..3000 {
+            match generator.next_event().unwrap().1 {
+                SourceEvent::Quote(quote) => {
+                    assert_eq!(growth, printed, "trades of {:?}", current);
+                    let previous = last.insert(quote.ticker.clone(), quote.clone()).unwrap();
+                    assert!(
+                        quote.volume >= previous.volume,
+                        "running volume of {:?}",
+                        quote
+                    );
+                    growth = quote.volume - previous.volume;
+                    printed = 0;
+                    current = Some((quote, previous.price));
+                }
+                SourceEvent::Trade(trade) => {
+                    let (quote, previous) = current.as_ref().expect("trades follow their quote");
+                    assert_eq!(trade.ticker, quote.ticker);
+                    assert_eq!(trade.price, quote.price);
+                    assert_eq!(trade.timestamp, quote.timestamp);
+                    assert_eq!(trade.trade_id, trade_id + 1);
+                    assert!(trade.size > 0);
+                    match quote.price.cmp(previous) {
+                        Ordering::Greater => assert_eq!(trade.aggressor, Side::Bid),
+                        Ordering::Less => assert_eq!(trade.aggressor, Side::Ask),
+                        Ordering::Equal => {}
+                    }
+                    trade_id = trade.trade_id;
+                    printed += trade.size;
+                }
+                SourceEvent::Book(_) => {}
+                event => panic!("unexpected {:?}", event),
+            }
+        }
+        assert!(trade_id > 500, "trades {}", trade_id);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::udp::QuoteSink;
+use parking_lot::RwLock;
+use quotes::StockQuote;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Default)]
+struct Quotes {
+    tickers: HashMap<String, VecDeque<StockQuote>>,
+    /// Newest timestamp recorded for any ticker, ages are counted from it
+    latest: u64,
+}
+
+/// Recent quotes of every ticker, bounded by depth and optionally by age
+#[derive(Clone)]
+pub(crate) struct QuoteHistory {
+    depth: usize,
+    window: Option<Duration>,
+    quotes: Arc<RwLock<Quotes>>,
+}
+
+impl QuoteHistory {
+    pub(crate) fn new(depth: usize, window: Option<Duration>) -> Self {
+        Self {
+            depth,
+            window,
+            quotes: Arc::new(RwLock::new(Quotes::default())),
+        }
+    }
+
+    /// Stored quotes of `ticker` with timestamp within `from..=to`, oldest first
+    pub(crate) fn query(
+        &self,
+        ticker: &str,
+        from: u64,
+        to: u64,
+        limit: Option<usize>,
+    ) -> Vec<StockQuote> {
+        let quotes = self.quotes.read();
+        let Some(buffer) = quotes.tickers.get(ticker) else {
+            return vec![];
+        };
+        buffer
+            .iter()
+            .skip_while(|q| q.timestamp < from)
+            .take_while(|q| q.timestamp <= to)
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+impl QuoteSink for QuoteHistory {
+    fn record(&self, quote: &StockQuote) {
+        if self.depth == 0 {
+            return;
+        }
+        let mut quotes = self.quotes.write();
+        quotes.latest = quotes.latest.max(quote.timestamp);
+        let buffer = quotes.tickers.entry(quote.ticker.clone()).or_default();
+        let idx = buffer.partition_point(|q| q.timestamp <= quote.timestamp);
+        buffer.insert(idx, quote.clone());
+        while buffer.len() > self.depth {
+            buffer.pop_front();
+        }
+        // Tickers without recent quotes age out too, not only the one just recorded
+        if let Some(window) = self.window {
+            let oldest = quotes.latest.saturating_sub(window.as_millis() as u64);
+            for buffer in quotes.tickers.values_mut() {
+                while buffer.front().is_some_and(|q| q.timestamp < oldest) {
+                    buffer.pop_front();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quotes::Price;
+
+    fn quote(price: u64, timestamp: u64) -> StockQuote {
+        let mut quote = StockQuote::new("AAPL", Price::from_cents(price), 1000);
+        quote.timestamp = timestamp;
+        quote
+    }
+
+    #[test]
+    fn test_query_in_timestamp_order() {
+        let history = QuoteHistory::new(10, None);
+        for (price, timestamp) in [(1, 100), (3, 300), (2, 200), (4, 400)] {
+            history.record(&quote(price, timestamp));
+        }
+        let prices: Vec<Price> = history
+            .query("AAPL", 150, 400, Some(2))
+            .into_iter()
+            .map(|q| q.price)
+            .collect();
+        assert_eq!(prices, [2, 3].map(Price::from_cents));
+        assert!(history.query("NFLX", 0, u64::MAX, None).is_empty());
+    }
+
+    #[test]
+    fn test_depth_and_window_limits() {
+        let history = QuoteHistory::new(3, None);
+        for i in 1..=5 {
+            history.record(&quote(i, i * 100));
+        }
+        assert_eq!(history.query("AAPL", 0, u64::MAX, None).len(), 3);
+
+        let history = QuoteHistory::new(100, Some(Duration::from_millis(250)));
+        for i in 1..=5 {
+            history.record(&quote(i, i * 100));
+        }
+        let prices: Vec<Price> = history
+            .query("AAPL", 0, u64::MAX, None)
+            .into_iter()
+            .map(|q| q.price)
+            .collect();
+        assert_eq!(prices, [3, 4, 5].map(Price::from_cents));
+
+        let mut quiet = quote(1, 500);
+        quiet.ticker = "NFLX".to_string();
+        history.record(&quiet);
+        assert_eq!(history.query("NFLX", 0, u64::MAX, None).len(), 1);
+        history.record(&quote(8, 800));
+        assert!(history.query("NFLX", 0, u64::MAX, None).is_empty());
+    }
+}
@@ -0,0 +1,413 @@
+use crate::udp::QuoteSink;
+use crossbeam::channel::{Receiver, Sender, TrySendError};
+use log::{error, info, warn};
+use quotes::StockQuote;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use std::{fs, io, thread};
+
+const SEGMENT_EXTENSION: &str = "jsonl";
+const INDEX_EXTENSION: &str = "idx";
+/// Every N-th record of a segment gets an index entry
+const INDEX_INTERVAL: u64 = 64;
+/// Index entry: timestamp and byte offset, both little-endian `u64`
+const INDEX_ENTRY_SIZE: usize = 16;
+/// Most quotes waiting for the journal writer, newer quotes are dropped until it catches up
+const MAX_PENDING_WRITES: usize = 100_000;
+/// Most HISTORY reads waiting for the journal reader
+const MAX_PENDING_READS: usize = 16;
+
+/// Where and how the journal is written
+#[derive(Debug, Clone)]
+pub(crate) struct JournalConfig {
+    pub(crate) dir: PathBuf,
+    pub(crate) segment_size: u64,
+    pub(crate) segment_age: Option<Duration>,
+}
+
+/// Hands published quotes over to the journal writer thread
+pub(crate) struct JournalWriter {
+    tx: Sender<StockQuote>,
+    /// Whether the writer fell behind and quotes are being dropped
+    behind: AtomicBool,
+}
+
+impl JournalWriter {
+    pub(crate) fn run(config: JournalConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        let (tx, rx) = crossbeam::channel::bounded(MAX_PENDING_WRITES);
+        thread::spawn(move || {
+            if let Err(e) = Journal::new(config).write_all(rx) {
+                error!("Journal stopped: {}", e);
+            }
+        });
+        Ok(Self {
+            tx,
+            behind: AtomicBool::new(false),
+        })
+    }
+}
+
+impl QuoteSink for JournalWriter {
+    /// Never blocks the publishing: a writer that fell behind loses quotes, not the stream
+    fn record(&self, quote: &StockQuote) {
+        match self.tx.try_send(quote.clone()) {
+            Ok(()) => {
+                if self.behind.swap(false, Ordering::Relaxed) {
+                    info!("Journal writer caught up");
+                }
+            }
+            Err(TrySendError::Full(_)) => {
+                if !self.behind.swap(true, Ordering::Relaxed) {
+                    warn!("Journal writer fell behind, dropping quotes");
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+/// Receives the quotes read by [`JournalReader`]
+type ReadReply = Box<dyn FnOnce(io::Result<Vec<StockQuote>>) + Send>;
+
+struct ReadRequest {
+    ticker: String,
+    from: u64,
+    to: u64,
+    limit: Option<usize>,
+    reply: ReadReply,
+}
+
+/// Reads the journal on its own thread, so slow reads never hold up control commands
+#[derive(Clone)]
+pub(crate) struct JournalReader {
+    tx: Sender<ReadRequest>,
+}
+
+impl JournalReader {
+    pub(crate) fn run(dir: PathBuf) -> Self {
+        let (tx, rx) = crossbeam::channel::bounded::<ReadRequest>(MAX_PENDING_READS);
+        thread::spawn(move || {
+            for request in rx {
+                let quotes = read(
+                    &dir,
+                    &request.ticker,
+                    request.from,
+                    request.to,
+                    request.limit,
+                );
+                (request.reply)(quotes);
+            }
+        });
+        Self { tx }
+    }
+
+    /// Queues a read of the quotes of `ticker` within `from..=to` and passes them to
+    /// `reply`. `false` if too many reads are waiting already
+    pub(crate) fn read(
+        &self,
+        ticker: &str,
+        from: u64,
+        to: u64,
+        limit: Option<usize>,
+        reply: impl FnOnce(io::Result<Vec<StockQuote>>) + Send + 'static,
+    ) -> bool {
+        let request = ReadRequest {
+            ticker: ticker.to_string(),
+            from,
+            to,
+            limit,
+            reply: Box::new(reply),
+        };
+        self.tx.try_send(request).is_ok()
+    }
+}
+
+struct Segment {
+    data: BufWriter<File>,
+    index: BufWriter<File>,
+    size: u64,
+    records: u64,
+    opened: Instant,
+}
+
+/// Append-only journal split into segments named after their first timestamp
+struct Journal {
+    config: JournalConfig,
+    segment: Option<Segment>,
+}
+
+impl Journal {
+    fn new(config: JournalConfig) -> Self {
+        Self {
+            config,
+            segment: None,
+        }
+    }
+
+    fn write_all(&mut self, rx: Receiver<StockQuote>) -> io::Result<()> {
+        while let Ok(quote) = rx.recv() {
+            self.append(&quote)?;
+            while let Ok(quote) = rx.try_recv() {
+                self.append(&quote)?;
+            }
+            self.flush()?;
+        }
+        self.flush()
+    }
+
+    fn append(&mut self, quote: &StockQuote) -> io::Result<()> {
+        if self.needs_rotation() {
+            self.rotate(quote.timestamp)?;
+        }
+        let Some(segment) = self.segment.as_mut() else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_vec(quote).map_err(io::Error::other)?;
+        line.push(b'\n');
+        if segment.records % INDEX_INTERVAL == 0 {
+            segment.index.write_all(&quote.timestamp.to_le_bytes())?;
+            segment.index.write_all(&segment.size.to_le_bytes())?;
+        }
+        segment.data.write_all(&line)?;
+        segment.size += line.len() as u64;
+        segment.records += 1;
+        Ok(())
+    }
+
+    fn needs_rotation(&self) -> bool {
+        match &self.segment {
+            None => true,
+            Some(segment) => {
+                segment.size >= self.config.segment_size
+                    || self
+                        .config
+                        .segment_age
+                        .is_some_and(|age| segment.opened.elapsed() >= age)
+            }
+        }
+    }
+
+    fn rotate(&mut self, timestamp: u64) -> io::Result<()> {
+        self.flush()?;
+        let data_path = segment_path(&self.config.dir, timestamp, SEGMENT_EXTENSION);
+        let index_path = segment_path(&self.config.dir, timestamp, INDEX_EXTENSION);
+        let open = |path: &Path| OpenOptions::new().create(true).append(true).open(path);
+        let data = open(&data_path)?;
+        let size = data.metadata()?.len();
+        if size > 0 {
+            warn!("Journal segment {:?} already exists", data_path);
+        }
+        info!("Journal segment {:?} opened", data_path);
+        self.segment = Some(Segment {
+            data: BufWriter::new(data),
+            index: BufWriter::new(open(&index_path)?),
+            size,
+            records: 0,
+            opened: Instant::now(),
+        });
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(segment) = self.segment.as_mut() {
+            segment.data.flush()?;
+            segment.index.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, timestamp: u64, extension: &str) -> PathBuf {
+    dir.join(format!("{:020}.{}", timestamp, extension))
+}
+
+/// First timestamps of all segments in `dir`, oldest first
+fn segments(dir: &Path) -> io::Result<Vec<u64>> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        if let Some(timestamp) = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.parse().ok())
+        {
+            segments.push(timestamp);
+        }
+    }
+    segments.sort_unstable();
+    Ok(segments)
+}
+
+/// Byte offset of the last indexed record published no later than `timestamp`
+fn seek_offset(dir: &Path, segment: u64, timestamp: u64) -> io::Result<u64> {
+    let index = match fs::read(segment_path(dir, segment, INDEX_EXTENSION)) {
+        Ok(index) => index,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut offset = 0;
+    for entry in index.chunks_exact(INDEX_ENTRY_SIZE) {
+        let (entry_timestamp, entry_offset) = entry.split_at(INDEX_ENTRY_SIZE / 2);
+        let entry_timestamp = u64::from_le_bytes(entry_timestamp.try_into().unwrap_or_default());
+        if entry_timestamp > timestamp {
+            break;
+        }
+        offset = u64::from_le_bytes(entry_offset.try_into().unwrap_or_default());
+    }
+    Ok(offset)
+}
+
+fn read_segment(dir: &Path, segment: u64, offset: u64) -> io::Result<Vec<StockQuote>> {
+    let mut file = File::open(segment_path(dir, segment, SEGMENT_EXTENSION))?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut quotes = Vec::new();
+    for line in BufReader::new(file).lines() {
+        // A record cut by a crash is skipped
+        if let Ok(quote) = serde_json::from_str(&line?) {
+            quotes.push(quote);
+        }
+    }
+    Ok(quotes)
+}
+
+/// Journaled quotes of `ticker` with timestamp within `from..=to`, oldest first
+fn read(
+    dir: &Path,
+    ticker: &str,
+    from: u64,
+    to: u64,
+    limit: Option<usize>,
+) -> io::Result<Vec<StockQuote>> {
+    let segments = segments(dir)?;
+    let first = segments.partition_point(|&s| s <= from).saturating_sub(1);
+    let limit = limit.unwrap_or(usize::MAX);
+    let mut quotes = Vec::new();
+    for &segment in segments[first..].iter().take_while(|&&s| s <= to) {
+        let offset = seek_offset(dir, segment, from)?;
+        for quote in read_segment(dir, segment, offset)? {
+            if quotes.len() == limit {
+                return Ok(quotes);
+            }
+            if quote.ticker == ticker && (from..=to).contains(&quote.timestamp) {
+                quotes.push(quote);
+            }
+        }
+    }
+    Ok(quotes)
+}
+
+/// Last journaled quote of each of `tickers`, searching from the newest segment
+pub(crate) fn last_quotes(
+    dir: &Path,
+    tickers: &[String],
+) -> io::Result<HashMap<String, StockQuote>> {
+    let mut missing: HashSet<&String> = tickers.iter().collect();
+    let mut last = HashMap::new();
+    if !dir.exists() {
+        return Ok(last);
+    }
+    for segment in segments(dir)?.into_iter().rev() {
+        if missing.is_empty() {
+            break;
+        }
+        let mut found = HashMap::new();
+        for quote in read_segment(dir, segment, 0)? {
+            if missing.contains(&quote.ticker) {
+                found.insert(quote.ticker.clone(), quote);
+            }
+        }
+        for (ticker, quote) in found {
+            missing.remove(&ticker);
+            last.insert(ticker, quote);
+        }
+    }
+    Ok(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quotes::Price;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("journal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn quote(ticker: &str, price: u64, timestamp: u64) -> StockQuote {
+        let mut quote = StockQuote::new(ticker, Price::from_cents(price), 1000);
+        quote.timestamp = timestamp;
+        quote
+    }
+
+    fn write(dir: &Path, quotes: &[StockQuote], segment_size: u64) {
+        let mut journal = Journal::new(JournalConfig {
+            dir: dir.to_path_buf(),
+            segment_size,
+            segment_age: None,
+        });
+        fs::create_dir_all(dir).unwrap();
+        for quote in quotes {
+            journal.append(quote).unwrap();
+        }
+        journal.flush().unwrap();
+    }
+
+    #[test]
+    fn test_rotation_and_seek() {
+        let dir = temp_dir("seek");
+        let quotes: Vec<StockQuote> = (0..500)
+            .map(|i| quote(if i % 2 == 0 { "AAPL" } else { "NFLX" }, i, 1000 + i))
+            .collect();
+        write(&dir, &quotes, 4096);
+        assert!(segments(&dir).unwrap().len() > 1);
+
+        let result = read(&dir, "AAPL", 1200, 1300, Some(10)).unwrap();
+        let prices: Vec<Price> = result.iter().map(|q| q.price).collect();
+        let expected: Vec<Price> = (200..220).step_by(2).map(Price::from_cents).collect();
+        assert_eq!(prices, expected);
+        let result = read(&dir, "NFLX", 1400, 2000, None).unwrap();
+        assert_eq!(result.len(), 50);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reader() {
+        let dir = temp_dir("reader");
+        write(&dir, &[quote("AAPL", 100, 1), quote("NFLX", 300, 2)], 64);
+        let reader = JournalReader::run(dir.clone());
+        let (tx, rx) = crossbeam::channel::bounded(1);
+        assert!(reader.read("NFLX", 0, 10, None, move |quotes| {
+            let _ = tx.send(quotes);
+        }));
+        let quotes = rx.recv().unwrap().unwrap();
+        assert_eq!(quotes, [quote("NFLX", 300, 2)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_last_quotes() {
+        let dir = temp_dir("last");
+        let quotes = vec![
+            quote("AAPL", 100, 1),
+            quote("NFLX", 300, 2),
+            quote("AAPL", 110, 3),
+        ];
+        write(&dir, &quotes, 64);
+        let tickers = vec!["AAPL".to_string(), "NFLX".to_string(), "MSFT".to_string()];
+        let last = last_quotes(&dir, &tickers).unwrap();
+        assert_eq!(last.len(), 2);
+        assert_eq!(last["AAPL"].price, Price::from_cents(110));
+        assert_eq!(last["NFLX"].price, Price::from_cents(300));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +1,37 @@
 extern crate core;
 
-use crate::variables::CHANNEL_SIZE;
+use crate::cache::QuoteCache;
+use crate::generator::events::{MarketEvents, load_scenario};
+use crate::generator::schedule::{Burst, Schedule, parse_rate};
+use crate::generator::session::{Calendar, TradingHalts};
+use crate::generator::{
+    ModelParams, PriceModel, QuoteGenerator, TickerParams, parse_non_negative, parse_reversion,
+};
+use crate::history::QuoteHistory;
+use crate::journal::{JournalConfig, JournalReader, JournalWriter};
+use crate::source::SourceKind;
+use crate::source::feed::FeedSource;
+use crate::source::replay::{ReplaySource, Speed};
+use crate::tickers::Tickers;
+use crate::udp::{Context, QuoteSink, UnknownTickers};
+use crate::variables::{CHANNEL_SIZE, METRICS_INTERVAL};
 use clap::Parser;
 use log::info;
-use std::path::PathBuf;
+use quotes::{Clock, FixedClock, QuoteSource, StreamKind, SystemClock, Universe, UniverseFormat};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::time::Duration;
 use std::{fs, io};
 
+mod cache;
 mod generator;
+mod history;
+mod journal;
+mod metrics;
+mod source;
 mod tcp;
+mod tickers;
 mod udp;
 mod variables;
 
@@ -26,29 +49,318 @@ struct Cli {
     #[clap(long)]
     #[arg(default_value = "7867")]
     udp_port: u16,
+    /// Ticker list, or a `.toml`/`.json` universe file with per-ticker settings
     #[clap(long)]
     #[arg(default_value = "resources/tickers.txt")]
     tickers_path: PathBuf,
+    #[clap(long, value_enum)]
+    #[arg(default_value = "warn")]
+    unknown_tickers: UnknownTickers,
+    /// Token a control session sends with `AUTH` to use HALT, RESUME and EVENT,
+    /// these commands are refused when unset
+    #[clap(long)]
+    admin_token: Option<String>,
+    /// Quotes kept per ticker for HISTORY requests
+    #[clap(long)]
+    #[arg(default_value = "1000")]
+    history_depth: usize,
+    /// Drop stored quotes older than this many seconds
+    #[clap(long)]
+    history_window: Option<u64>,
+    /// Append every quote to segment files in this directory and resume prices from it
+    #[clap(long)]
+    journal: Option<PathBuf>,
+    /// Start a new journal segment once the current one reaches this many bytes
+    #[clap(long)]
+    #[arg(default_value = "67108864")]
+    journal_segment_size: u64,
+    /// Start a new journal segment after this many seconds
+    #[clap(long)]
+    journal_segment_age: Option<u64>,
+    /// Where quotes come from, `replay` when `--replay` is set and `random` otherwise
+    #[clap(long, value_enum)]
+    source: Option<SourceKind>,
+    /// Address the `socket` source listens on for the quote feed
+    #[clap(long)]
+    #[arg(default_value = "127.0.0.1:7000")]
+    feed_addr: SocketAddr,
+    /// Publish quotes recorded in a JSON-lines or CSV file instead of generating them
+    #[clap(long)]
+    replay: Option<PathBuf>,
+    /// Replay pace: a multiplier of the recorded timing (1x, 10x) or max
+    #[clap(long)]
+    #[arg(default_value = "1x")]
+    replay_speed: Speed,
+    /// Start the replay over once the file is exhausted
+    #[clap(long)]
+    replay_loop: bool,
+    /// Seed for the `random` source, the same seed reproduces the same quotes
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Timestamp quotes with a clock reading START first and STEP milliseconds more on
+    /// every reading, e.g. `1700000000000:10`, instead of the wall clock
+    #[clap(long)]
+    fixed_clock: Option<FixedClock>,
+    /// How the `random` source moves prices
+    #[clap(long, value_enum)]
+    #[arg(default_value_t)]
+    price_model: PriceModel,
+    /// Expected log return per quote of a ticker, per generator step for sector tickers
+    #[clap(long)]
+    #[arg(default_value = "0")]
+    drift: f64,
+    /// Standard deviation of the log return per quote, per step for sector tickers
+    #[clap(long, value_parser = parse_non_negative)]
+    #[arg(default_value = "0.0005")]
+    volatility: f64,
+    /// Share of the distance to the mean price recovered per quote (mean-reverting model)
+    #[clap(long, value_parser = parse_reversion)]
+    #[arg(default_value = "0.01")]
+    reversion: f64,
+    /// Quotes per second published by the `random` source
+    #[clap(long, value_parser = parse_rate)]
+    #[arg(default_value = "100")]
+    rate: f64,
+    /// Repeatable burst of the `random` source as RATE:DURATION:PERIOD,
+    /// e.g. `10000:5s:1m` is 10k quotes/s for 5 seconds every minute
+    #[clap(long)]
+    burst: Vec<Burst>,
+    /// Daily UTC trading hours of the `random` source as PRE_OPEN,OPEN,CLOSING_AUCTION,CLOSE,
+    /// e.g. `08:00,09:30,15:50,16:00`, the market never closes when unset
+    #[clap(long)]
+    calendar: Option<Calendar>,
+    /// Per-ticker model parameters, e.g. `AAPL:price=180.00,drift=0.0001,volatility=0.001,mean=170.00`
+    #[clap(long)]
+    ticker_params: Vec<TickerParams>,
+    /// Market events of the `random` source, one per line, e.g. `+30s NVDA drop 15%`
+    #[clap(long)]
+    scenario: Option<PathBuf>,
+    /// Order book levels per side simulated by the `random` source, 0 turns depth off
+    #[clap(long)]
+    #[arg(default_value = "5")]
+    depth_levels: usize,
+    /// Publish full depth snapshots of every ticker after this many seconds, at least 1
+    #[clap(long, value_parser = clap::value_parser!(u64).range(1..))]
+    #[arg(default_value = "5")]
+    depth_snapshot_interval: u64,
 }
 
 fn main() {
     let cli = Cli::parse();
     env_logger::init();
-    if let Err(e) = start(cli.tcp_port, cli.udp_port, cli.tickers_path) {
+    if let Err(e) = start(cli) {
         eprintln!("{}", e);
         exit(-1);
     }
 }
 
-fn start(tcp_port: u16, udp_port: u16, tickers_path: PathBuf) -> io::Result<()> {
-    let data = fs::read_to_string(tickers_path)?;
-    let tickers = quotes::parse_tickers(&data);
+fn start(cli: Cli) -> io::Result<()> {
+    let Source {
+        quotes,
+        tickers,
+        halts,
+        events,
+        published,
+    } = build_source(&cli)?;
+
+    let mut sinks: Vec<Box<dyn QuoteSink>> = Vec::new();
+    if let Some(dir) = &cli.journal {
+        sinks.push(Box::new(JournalWriter::run(JournalConfig {
+            dir: dir.clone(),
+            segment_size: cli.journal_segment_size,
+            segment_age: cli.journal_segment_age.map(Duration::from_secs),
+        })?));
+    }
 
-    info!("Starting server on TCP:{}/UDP:{}", tcp_port, udp_port);
-    let command_rx = tcp::run(&format!("127.0.0.1:{}", tcp_port))?;
+    info!(
+        "Starting server on TCP:{}/UDP:{}",
+        cli.tcp_port, cli.udp_port
+    );
+    let command_rx = tcp::run(&format!("127.0.0.1:{}", cli.tcp_port), cli.admin_token)?;
     let (stock_tx, stock_rx) = crossbeam::channel::bounded(CHANNEL_SIZE);
-    generator::run(tickers, stock_tx.clone());
+    let clock: Box<dyn Clock> = match cli.fixed_clock {
+        Some(clock) => Box::new(clock),
+        None => Box::new(SystemClock),
+    };
+    source::run(quotes, clock, stock_tx);
+    metrics::run(METRICS_INTERVAL);
+
+    let (shutdown_tx, shutdown_rx) = crossbeam::channel::bounded(1);
+    ctrlc::set_handler(move || {
+        let _ = shutdown_tx.try_send(());
+    })
+    .map_err(io::Error::other)?;
 
-    udp::run(udp_port, command_rx, stock_tx, stock_rx)?;
+    let context = Context {
+        tickers,
+        unknown_tickers: cli.unknown_tickers,
+        cache: QuoteCache::new(),
+        history: QuoteHistory::new(
+            cli.history_depth,
+            cli.history_window.map(Duration::from_secs),
+        ),
+        journal: cli.journal.clone().map(JournalReader::run),
+        halts,
+        events,
+        published,
+    };
+    udp::run(
+        cli.udp_port,
+        context,
+        sinks,
+        command_rx,
+        stock_rx,
+        shutdown_rx,
+    )?;
     Ok(())
 }
+
+/// The quote source, its tickers and the controls it accepts
+struct Source {
+    quotes: Box<dyn QuoteSource>,
+    tickers: Tickers,
+    halts: Option<TradingHalts>,
+    events: Option<MarketEvents>,
+    /// Market data the source publishes
+    published: StreamKind,
+}
+
+impl Source {
+    fn new(quotes: Box<dyn QuoteSource>, tickers: Vec<String>) -> Self {
+        Self {
+            quotes,
+            tickers: Tickers::new(tickers),
+            halts: None,
+            events: None,
+            published: StreamKind::TRADES,
+        }
+    }
+}
+
+fn build_source(cli: &Cli) -> io::Result<Source> {
+    let kind = cli.source.unwrap_or(match cli.replay {
+        Some(_) => SourceKind::Replay,
+        None => SourceKind::Random,
+    });
+    if kind != SourceKind::Random && cli.scenario.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--scenario is only supported by the random source",
+        ));
+    }
+    let source: Source = match kind {
+        SourceKind::Replay => {
+            let Some(path) = &cli.replay else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--replay <file> is required for the replay source",
+                ));
+            };
+            info!("Replaying {:?} at {}", path, cli.replay_speed);
+            let replay = ReplaySource::open(path, cli.replay_speed, cli.replay_loop)?;
+            let tickers = replay.tickers();
+            Source::new(Box::new(replay), tickers)
+        }
+        SourceKind::Random => {
+            let universe = load_universe(&cli.tickers_path)?;
+            let halts = TradingHalts::new();
+            let tickers = universe.names();
+            let mut generator = match cli.seed {
+                Some(seed) => {
+                    info!("Generating quotes with seed {}", seed);
+                    QuoteGenerator::seeded(universe.tickers, seed)
+                }
+                None => QuoteGenerator::new(universe.tickers),
+            }
+            .with_correlations(&universe.correlations)?
+            .with_model(
+                cli.price_model,
+                ModelParams {
+                    drift: cli.drift,
+                    volatility: cli.volatility,
+                    reversion: cli.reversion,
+                    ..ModelParams::default()
+                },
+                &cli.ticker_params,
+            )
+            .with_schedule(Schedule::new(cli.rate, cli.burst.clone()))
+            .with_session(cli.calendar, halts.clone())
+            .with_depth(
+                cli.depth_levels,
+                Duration::from_secs(cli.depth_snapshot_interval),
+            );
+            if let Some(clock) = cli.fixed_clock {
+                generator = generator.with_clock(Box::new(clock));
+            }
+            info!("Publishing {} quotes/s", cli.rate);
+            for burst in &cli.burst {
+                info!("Burst of {}", burst);
+            }
+            if let Some(dir) = &cli.journal {
+                let last_quotes = journal::last_quotes(dir, &tickers)?;
+                info!(
+                    "Resuming {} tickers from journal {:?}",
+                    last_quotes.len(),
+                    dir
+                );
+                generator.resume(last_quotes);
+            }
+            if let Some(path) = &cli.scenario {
+                let scenario = load_scenario(path)?;
+                info!(
+                    "Scheduling {} market events from {:?}",
+                    scenario.len(),
+                    path
+                );
+                generator.script(scenario).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{}: {}", path.display(), e),
+                    )
+                })?;
+            }
+            let events = generator.events();
+            Source {
+                tickers: generator.tickers(),
+                quotes: Box::new(generator),
+                halts: Some(halts),
+                events: Some(events),
+                published: StreamKind {
+                    trades: true,
+                    quotes: true,
+                    depth: cli.depth_levels > 0,
+                    prints: true,
+                },
+            }
+        }
+        SourceKind::Stdin => Source::new(
+            Box::new(FeedSource::stdin()),
+            load_tickers(&cli.tickers_path)?,
+        ),
+        SourceKind::Socket => Source::new(
+            Box::new(FeedSource::listen(cli.feed_addr)?),
+            load_tickers(&cli.tickers_path)?,
+        ),
+    };
+    Ok(source)
+}
+
+fn load_tickers(path: &Path) -> io::Result<Vec<String>> {
+    Ok(load_universe(path)?.names())
+}
+
+/// Reads a `.toml` or `.json` universe file, or a plain list of tickers
+fn load_universe(path: &Path) -> io::Result<Universe> {
+    let data = fs::read_to_string(path)?;
+    let format = UniverseFormat::from_extension(
+        path.extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default(),
+    );
+    quotes::parse_universe(&data, format).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
@@ -0,0 +1,110 @@
+use log::info;
+use std::fmt;
+use std::fmt::Formatter;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Server-wide counters, reported to the log periodically
+pub(crate) static METRICS: Metrics = Metrics::new();
+
+/// Why a subscriber's worker was stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StopReason {
+    Keepalive,
+    Unsubscribe,
+    Shutdown,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Keepalive => write!(f, "keepalive expired"),
+            StopReason::Unsubscribe => write!(f, "unsubscribed"),
+            StopReason::Shutdown => write!(f, "server shutdown"),
+        }
+    }
+}
+
+pub(crate) struct Metrics {
+    clients_started: AtomicU64,
+    stopped_keepalive: AtomicU64,
+    stopped_unsubscribe: AtomicU64,
+    stopped_shutdown: AtomicU64,
+    quotes_dropped: AtomicU64,
+    quotes_published: AtomicU64,
+    /// Largest delay behind the source schedule since the last report, microseconds
+    max_lag: AtomicU64,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            clients_started: AtomicU64::new(0),
+            stopped_keepalive: AtomicU64::new(0),
+            stopped_unsubscribe: AtomicU64::new(0),
+            stopped_shutdown: AtomicU64::new(0),
+            quotes_dropped: AtomicU64::new(0),
+            quotes_published: AtomicU64::new(0),
+            max_lag: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn client_started(&self) {
+        self.clients_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn client_stopped(&self, reason: StopReason) {
+        let counter = match reason {
+            StopReason::Keepalive => &self.stopped_keepalive,
+            StopReason::Unsubscribe => &self.stopped_unsubscribe,
+            StopReason::Shutdown => &self.stopped_shutdown,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn quote_dropped(&self) {
+        self.quotes_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A quote left the source `lag` after its scheduled time
+    pub(crate) fn quote_published(&self, lag: Duration) {
+        self.quotes_published.fetch_add(1, Ordering::Relaxed);
+        self.max_lag
+            .fetch_max(lag.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "clients started: {}, stopped (keepalive: {}, unsubscribe: {}, shutdown: {}), quotes published: {}, dropped: {}",
+            self.clients_started.load(Ordering::Relaxed),
+            self.stopped_keepalive.load(Ordering::Relaxed),
+            self.stopped_unsubscribe.load(Ordering::Relaxed),
+            self.stopped_shutdown.load(Ordering::Relaxed),
+            self.quotes_published.load(Ordering::Relaxed),
+            self.quotes_dropped.load(Ordering::Relaxed),
+        )
+    }
+}
+
+pub(crate) fn run(interval: Duration) {
+    thread::spawn(move || {
+        let mut published = 0;
+        let mut reported = Instant::now();
+        loop {
+            thread::sleep(interval);
+            let total = METRICS.quotes_published.load(Ordering::Relaxed);
+            let rate = (total - published) as f64 / reported.elapsed().as_secs_f64();
+            let max_lag = Duration::from_micros(METRICS.max_lag.swap(0, Ordering::Relaxed));
+            (published, reported) = (total, Instant::now());
+            info!("Metrics: {}", METRICS);
+            info!(
+                "Source: {:.0} quotes/s, max lag behind schedule {:?}",
+                rate, max_lag
+            );
+        }
+    });
+}
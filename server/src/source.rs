@@ -0,0 +1,149 @@
+use crate::metrics::METRICS;
+use crate::udp::ClientCommand;
+use clap::ValueEnum;
+use crossbeam::channel::Sender;
+use log::info;
+use quotes::{Clock, Price, QuoteSource, SourceEvent, StockQuote};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub(crate) mod feed;
+pub(crate) mod replay;
+
+/// Falling further behind the schedule restarts it from now, so the source is not
+/// drained in a burst to catch up; every quote is still published
+const MAX_LAG: Duration = Duration::from_secs(1);
+
+/// Quote sources selectable from the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum SourceKind {
+    /// Random prices for the tickers list
+    Random,
+    /// Recorded quotes from the `--replay` file
+    Replay,
+    /// JSON or CSV records read from standard input
+    Stdin,
+    /// JSON or CSV records sent to the `--feed-addr` TCP socket
+    Socket,
+}
+
+/// Publishes quotes of `source`, delays are counted from the previous deadline
+/// so sleep overshoot does not accumulate at high rates.
+///
+/// Events are generated before their delay, so an event published after one is
+/// stamped by `clock` when it is sent. Events following it without a delay were
+/// generated together with it and get the same timestamp
+pub(crate) fn run(
+    mut source: Box<dyn QuoteSource>,
+    mut clock: Box<dyn Clock>,
+    stock_tx: Sender<ClientCommand>,
+) {
+    thread::spawn(move || {
+        let mut deadline = Instant::now();
+        let mut stamp = None;
+        while let Some((delay, mut event)) = source.next_event() {
+            let now = Instant::now();
+            if delay.is_zero() {
+                deadline = now;
+            } else {
+                deadline += delay;
+                if deadline > now {
+                    thread::sleep(deadline - now);
+                }
+            }
+            let lag = Instant::now().saturating_duration_since(deadline);
+            if lag > MAX_LAG {
+                deadline = Instant::now();
+            }
+            if !delay.is_zero() {
+                stamp = Some(clock.now());
+            }
+            if let Some(stamp) = stamp {
+                event.set_timestamp(stamp);
+            }
+            let quote = matches!(event, SourceEvent::Quote(_));
+            let command = match event {
+                SourceEvent::Quote(quote) => ClientCommand::Send(quote),
+                SourceEvent::Trade(trade) => ClientCommand::Trade(trade),
+                SourceEvent::Book(book) => ClientCommand::Book(book),
+                SourceEvent::Depth(update) => ClientCommand::Depth(update),
+                SourceEvent::DepthSnapshot(snapshot) => ClientCommand::DepthSnapshot(snapshot),
+                SourceEvent::Status(status) => ClientCommand::Status(status),
+                SourceEvent::Action(action) => ClientCommand::Action(action),
+                SourceEvent::Idle => continue,
+            };
+            if stock_tx.send(command).is_err() {
+                break;
+            }
+            if quote {
+                METRICS.quote_published(lag);
+            }
+        }
+        info!("Quote source exhausted");
+    });
+}
+
+/// Parses a JSON record or a `ticker,price,volume,timestamp` CSV line. Like in JSON,
+/// a decimal price is taken as is and an integer one as hundredths
+pub(crate) fn parse_record(line: &str) -> Option<StockQuote> {
+    if line.starts_with('{') {
+        return serde_json::from_str(line).ok();
+    }
+    let mut fields = line.split(',').map(str::trim);
+    let ticker = fields.next()?;
+    let price = fields.next()?;
+    let price = Price::parse_cents_or_decimal(price).ok()?;
+    let volume = fields.next()?.parse().ok()?;
+    let timestamp = fields.next()?.parse().ok()?;
+    let mut quote = StockQuote::new(ticker, price, volume);
+    quote.timestamp = timestamp;
+    Some(quote)
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quotes::{FixedClock, Side, Trade};
+    use std::collections::VecDeque;
+
+    struct Scripted(VecDeque<(Duration, SourceEvent)>);
+
+    impl QuoteSource for Scripted {
+        fn next_quote(&mut self) -> Option<(Duration, StockQuote)> {
+            None
+        }
+
+        fn next_event(&mut self) -> Option<(Duration, SourceEvent)> {
+            self.0.pop_front()
+        }
+    }
+
+    #[test]
+    fn test_run_stamps_delayed_events() {
+        let quote = StockQuote::new("AAPL", Price::from_cents(18000), 100);
+        let trade = Trade::new("AAPL", 1, quote.price, 100, Side::Bid, quote.timestamp);
+        let delay = Duration::from_millis(50);
+        let source = Scripted(VecDeque::from([
+            (delay, SourceEvent::Quote(quote)),
+            (Duration::ZERO, SourceEvent::Trade(trade)),
+        ]));
+        let (tx, rx) = crossbeam::channel::unbounded();
+        run(Box::new(source), Box::new(FixedClock::new(42_000, 10)), tx);
+
+        let ClientCommand::Send(quote) = rx.recv().unwrap() else {
+            panic!("quote expected");
+        };
+        assert_eq!(quote.timestamp, 42_000);
+        let ClientCommand::Trade(trade) = rx.recv().unwrap() else {
+            panic!("trade expected");
+        };
+        assert_eq!(trade.timestamp, quote.timestamp);
+    }
+}
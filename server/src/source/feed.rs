@@ -0,0 +1,108 @@
+use crate::source::{now_millis, parse_record};
+use log::{info, warn};
+use quotes::{QuoteSource, StockQuote};
+use std::io::{BufRead, BufReader};
+use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
+use std::{io, iter, thread};
+
+/// Pause after a failed accept, so a persistent error does not spin the source thread
+const ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Publishes quotes as soon as they are read, one JSON or CSV record per line
+pub(crate) struct FeedSource {
+    lines: Box<dyn Iterator<Item = String> + Send>,
+}
+
+impl FeedSource {
+    pub(crate) fn stdin() -> Self {
+        Self::new(BufReader::new(io::stdin()).lines().map_while(Result::ok))
+    }
+
+    /// Accepts feed connections on `address` one after another
+    pub(crate) fn listen(address: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        info!("Waiting for quote feed on {}", address);
+        let lines = iter::repeat_with(move || match listener.accept() {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                warn!("Unable to accept quote feed connection: {}", e);
+                thread::sleep(ACCEPT_BACKOFF);
+                None
+            }
+        })
+        .flatten()
+        .flat_map(|(stream, peer)| {
+            info!("Quote feed connected from {}", peer);
+            BufReader::new(stream).lines().map_while(Result::ok)
+        });
+        Ok(Self::new(lines))
+    }
+
+    fn new(lines: impl Iterator<Item = String> + Send + 'static) -> Self {
+        Self {
+            lines: Box::new(lines),
+        }
+    }
+}
+
+impl QuoteSource for FeedSource {
+    fn next_quote(&mut self) -> Option<(Duration, StockQuote)> {
+        for line in self.lines.by_ref() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match parse_record(line) {
+                Some(mut quote) => {
+                    quote.timestamp = now_millis();
+                    return Some((Duration::ZERO, quote));
+                }
+                None => warn!("Invalid feed record skipped: {}", line),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quotes::Price;
+    use rand::random_range;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::thread;
+
+    #[test]
+    fn test_feed_skips_invalid_records() {
+        let lines = [
+            "AAPL,100,10,1",
+            "",
+            "garbage",
+            r#"{"ticker":"NFLX","price":2,"volume":3,"timestamp":4}"#,
+        ];
+        let mut feed = FeedSource::new(lines.into_iter().map(String::from));
+        let tickers: Vec<String> = iter::from_fn(|| feed.next_quote())
+            .map(|(_, quote)| quote.ticker)
+            .collect();
+        assert_eq!(tickers, vec!["AAPL", "NFLX"]);
+    }
+
+    #[test]
+    fn test_socket_feed() {
+        let address = SocketAddr::from(([127, 0, 0, 1], random_range(9000..10000)));
+        let mut feed = FeedSource::listen(address).unwrap();
+        thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(b"AAPL,100,10,1\nNFLX,200,20,2\n").unwrap();
+        });
+        let (delay, quote) = feed.next_quote().unwrap();
+        assert!(delay.is_zero());
+        assert_eq!(
+            (quote.ticker.as_str(), quote.price),
+            ("AAPL", Price::from_cents(100))
+        );
+        assert_eq!(feed.next_quote().unwrap().1.ticker, "NFLX");
+    }
+}
@@ -0,0 +1,197 @@
+use crate::source::{now_millis, parse_record};
+use quotes::{QuoteSource, StockQuote};
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Formatter;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use std::{fs, io, mem};
+
+/// Replay pace relative to the recorded inter-arrival times
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Speed {
+    Multiplier(f64),
+    /// No delays between quotes
+    Max,
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("max") {
+            return Ok(Speed::Max);
+        }
+        let multiplier = s.strip_suffix(['x', 'X']).unwrap_or(s);
+        match multiplier.parse::<f64>() {
+            Ok(multiplier) if multiplier > 0.0 && multiplier.is_finite() => {
+                Ok(Speed::Multiplier(multiplier))
+            }
+            _ => Err(format!(
+                "invalid speed '{}', expected e.g. 1x, 10x or max",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Speed::Multiplier(multiplier) => write!(f, "{}x", multiplier),
+            Speed::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// Publishes recorded quotes, restamped with the current time
+pub(crate) struct ReplaySource {
+    quotes: Vec<StockQuote>,
+    position: usize,
+    previous: Option<u64>,
+    speed: Speed,
+    looping: bool,
+}
+
+impl ReplaySource {
+    pub(crate) fn open(path: &Path, speed: Speed, looping: bool) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        Ok(Self::new(parse_records(&data)?, speed, looping))
+    }
+
+    fn new(quotes: Vec<StockQuote>, speed: Speed, looping: bool) -> Self {
+        Self {
+            quotes,
+            position: 0,
+            previous: None,
+            speed,
+            looping,
+        }
+    }
+
+    /// Tickers of the recording in order of first appearance
+    pub(crate) fn tickers(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.quotes
+            .iter()
+            .filter(|q| seen.insert(q.ticker.as_str()))
+            .map(|q| q.ticker.clone())
+            .collect()
+    }
+
+    fn delay(&self, timestamp: u64) -> Duration {
+        let Some(previous) = self.previous else {
+            return Duration::ZERO;
+        };
+        let recorded = Duration::from_millis(timestamp.saturating_sub(previous));
+        match self.speed {
+            Speed::Multiplier(multiplier) => recorded.div_f64(multiplier),
+            Speed::Max => Duration::ZERO,
+        }
+    }
+}
+
+impl QuoteSource for ReplaySource {
+    fn next_quote(&mut self) -> Option<(Duration, StockQuote)> {
+        if self.position == self.quotes.len() {
+            if !self.looping || self.quotes.is_empty() {
+                return None;
+            }
+            self.position = 0;
+            self.previous = None;
+        }
+        let mut quote = self.quotes[self.position].clone();
+        self.position += 1;
+        let delay = self.delay(quote.timestamp);
+        self.previous = Some(quote.timestamp);
+        quote.timestamp = now_millis() + delay.as_millis() as u64;
+        Some((delay, quote))
+    }
+}
+
+/// Parses JSON lines or `ticker,price,volume,timestamp` CSV with an optional header,
+/// only the first line can be a header
+fn parse_records(data: &str) -> io::Result<Vec<StockQuote>> {
+    let mut quotes = Vec::new();
+    let mut first = true;
+    for (i, line) in data.lines().map(str::trim).enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let header = mem::take(&mut first) && !line.starts_with('{');
+        match parse_record(line) {
+            Some(quote) => quotes.push(quote),
+            None if header => continue,
+            None => return Err(invalid_record(i, line)),
+        }
+    }
+    Ok(quotes)
+}
+
+fn invalid_record(line: usize, details: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("Invalid replay record at line {}: {}", line + 1, details),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quotes::Price;
+
+    const CSV: &str =
+        "ticker,price,volume,timestamp\nAAPL,100,10,1000\nNFLX,200,20,1500\nAAPL,1.01,11,2500\n";
+
+    #[test]
+    fn test_parse_json_and_csv() {
+        let csv = parse_records(CSV).unwrap();
+        let json: String = csv
+            .iter()
+            .map(|q| serde_json::to_string(q).unwrap() + "\n")
+            .collect();
+        assert_eq!(parse_records(&json).unwrap(), csv);
+        assert_eq!(csv.len(), 3);
+        assert!(parse_records("AAPL,100,10,1000\nAAPL,oops,1,1").is_err());
+        assert!(
+            parse_records("ticker,price,volume,timestamp\ntime,ticker\nAAPL,100,10,1").is_err()
+        );
+        assert_eq!(parse_records("\nheader\nAAPL,100,10,1").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_speed_and_loop() {
+        let quotes = parse_records(CSV).unwrap();
+        let mut source = ReplaySource::new(quotes.clone(), Speed::Multiplier(10.0), true);
+        assert_eq!(source.tickers(), vec!["AAPL", "NFLX"]);
+        let delays: Vec<Duration> = (0..4).map(|_| source.next_quote().unwrap().0).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::ZERO,
+                Duration::from_millis(50),
+                Duration::from_millis(100),
+                Duration::ZERO
+            ]
+        );
+
+        let mut source = ReplaySource::new(quotes, Speed::Max, false);
+        let prices: Vec<Price> = std::iter::from_fn(|| source.next_quote())
+            .map(|(delay, quote)| {
+                assert!(delay.is_zero());
+                quote.price
+            })
+            .collect();
+        assert_eq!(prices, [100, 200, 101].map(Price::from_cents));
+    }
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!("10x".parse::<Speed>(), Ok(Speed::Multiplier(10.0)));
+        assert_eq!("0.5".parse::<Speed>(), Ok(Speed::Multiplier(0.5)));
+        assert_eq!("max".parse::<Speed>(), Ok(Speed::Max));
+        assert!("0x".parse::<Speed>().is_err());
+    }
+}
@@ -1,21 +1,68 @@
+use crate::generator::events::ScriptedEvent;
 use core::fmt;
-use log::error;
+use crossbeam::channel::{Receiver, Sender};
+use log::{error, info};
+use parking_lot::Mutex;
+use quotes::StreamKind;
+use std::collections::HashMap;
 use std::fmt::Formatter;
 use std::io::{BufRead, BufReader, ErrorKind, Write};
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::str::FromStr;
-use std::sync::mpsc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
 use std::{io, thread};
 
 const UDP_PREFIX_LEN: usize = 6;
+const TAG_PREFIX: char = '#';
+const END_OF_LIST: &str = "END";
+
+/// Control session that started each UDP stream, a closing session ends only its own
+type StreamOwners = Arc<Mutex<HashMap<SocketAddr, u64>>>;
 
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) enum Command {
+    /// Starts a stream of `kind` market data, trades unless given
     Stream {
         address: SocketAddr,
         tickers: Vec<String>,
+        kind: StreamKind,
+    },
+    /// Adds tickers to an existing stream
+    Add {
+        address: SocketAddr,
+        tickers: Vec<String>,
+    },
+    /// Removes tickers from an existing stream
+    Remove {
+        address: SocketAddr,
+        tickers: Vec<String>,
+    },
+    /// Ends the whole stream when `tickers` is empty
+    Unsubscribe {
+        address: SocketAddr,
+        tickers: Vec<String>,
+    },
+    /// Lists tickers available on the server
+    List,
+    /// Latest quote of each ticker, or of every ticker when empty
+    Snapshot { tickers: Vec<String> },
+    /// Stored quotes of a ticker published within `from..=to` (unix millis)
+    History {
+        ticker: String,
+        from: u64,
+        to: u64,
+        limit: Option<usize>,
     },
+    /// Suspends trading in a ticker
+    Halt { ticker: String },
+    /// Resumes trading in a halted ticker
+    Resume { ticker: String },
+    /// Injects a market event into the generator
+    Event { event: ScriptedEvent },
+    /// Allows the admin commands `HALT`, `RESUME` and `EVENT` in the control session
+    Auth { token: String },
+    /// Closes the control session
+    Quit,
 }
 
 impl FromStr for Command {
@@ -29,35 +76,176 @@ impl FromStr for Command {
         };
         match command {
             "STREAM" => {
-                let address = parts.next().ok_or(bad_request(s))?.to_string();
-                if !address.starts_with("udp://") {
-                    return Err(bad_request(s));
-                }
-                let address =
-                    SocketAddr::from_str(&address[UDP_PREFIX_LEN..]).map_err(|_| bad_request(s))?;
-                let tickers = parts
-                    .next()
-                    .ok_or(bad_request(s))?
-                    .split(",")
-                    .map(ToString::to_string)
-                    .collect();
-                Ok(Command::Stream { address, tickers })
+                let address = parse_address(parts.next(), s)?;
+                let tickers = parse_tickers(parts.next().ok_or(bad_request(s))?, s)?;
+                let kind = match parts.next() {
+                    Some(kind) => kind.parse().map_err(|_| bad_request(s))?,
+                    None => StreamKind::default(),
+                };
+                Ok(Command::Stream {
+                    address,
+                    tickers,
+                    kind,
+                })
+            }
+            "ADD" => {
+                let address = parse_address(parts.next(), s)?;
+                let tickers = parse_tickers(parts.next().ok_or(bad_request(s))?, s)?;
+                Ok(Command::Add { address, tickers })
+            }
+            "REMOVE" => {
+                let address = parse_address(parts.next(), s)?;
+                let tickers = parse_tickers(parts.next().ok_or(bad_request(s))?, s)?;
+                Ok(Command::Remove { address, tickers })
+            }
+            "UNSUBSCRIBE" => {
+                let address = parse_address(parts.next(), s)?;
+                let tickers = match parts.next() {
+                    Some(tickers) => parse_tickers(tickers, s)?,
+                    None => vec![],
+                };
+                Ok(Command::Unsubscribe { address, tickers })
+            }
+            "LIST" => Ok(Command::List),
+            "SNAPSHOT" => {
+                let tickers = match parts.next() {
+                    Some(tickers) => parse_tickers(tickers, s)?,
+                    None => vec![],
+                };
+                Ok(Command::Snapshot { tickers })
+            }
+            "HISTORY" => {
+                let ticker = parts.next().ok_or(bad_request(s))?.to_string();
+                let from = parse_number(parts.next(), s)?;
+                let to = parse_number(parts.next(), s)?;
+                let limit = match parts.next() {
+                    Some(limit) => Some(parse_number(Some(limit), s)?),
+                    None => None,
+                };
+                Ok(Command::History {
+                    ticker,
+                    from,
+                    to,
+                    limit,
+                })
             }
+            "HALT" => {
+                let ticker = parts.next().ok_or(bad_request(s))?.to_string();
+                Ok(Command::Halt { ticker })
+            }
+            "RESUME" => {
+                let ticker = parts.next().ok_or(bad_request(s))?.to_string();
+                Ok(Command::Resume { ticker })
+            }
+            "EVENT" => {
+                let event = s[command.len()..]
+                    .parse()
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+                Ok(Command::Event { event })
+            }
+            "AUTH" => {
+                let token = parts.next().ok_or(bad_request(s))?.to_string();
+                Ok(Command::Auth { token })
+            }
+            "QUIT" => Ok(Command::Quit),
             _ => Err(bad_request(s)),
         }
     }
 }
 
+impl Command {
+    /// Commands that change the market rather than a stream, allowed after `AUTH`
+    fn is_admin(&self) -> bool {
+        matches!(
+            self,
+            Command::Halt { .. } | Command::Resume { .. } | Command::Event { .. }
+        )
+    }
+}
+
+fn parse_address(address: Option<&str>, s: &str) -> io::Result<SocketAddr> {
+    let address = address.ok_or(bad_request(s))?;
+    if !address.starts_with("udp://") {
+        return Err(bad_request(s));
+    }
+    SocketAddr::from_str(&address[UDP_PREFIX_LEN..]).map_err(|_| bad_request(s))
+}
+
+fn parse_number<T: FromStr>(number: Option<&str>, s: &str) -> io::Result<T> {
+    number
+        .ok_or(bad_request(s))?
+        .parse()
+        .map_err(|_| bad_request(s))
+}
+
+/// Comma separated tickers, an empty entry is an error rather than skipped: a list
+/// of nothing but empty entries would read as no list at all
+fn parse_tickers(tickers: &str, s: &str) -> io::Result<Vec<String>> {
+    tickers
+        .split(',')
+        .map(|ticker| match ticker {
+            "" => Err(bad_request(s)),
+            ticker => Ok(ticker.to_string()),
+        })
+        .collect()
+}
+
 impl fmt::Display for Command {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Command::Stream { address, tickers } => {
+            Command::Stream {
+                address,
+                tickers,
+                kind,
+            } => {
                 f.write_fmt(format_args!(
                     "STREAM udp://{} {}",
                     address,
                     tickers.join(",")
                 ))?;
+                if *kind != StreamKind::default() {
+                    f.write_fmt(format_args!(" {}", kind))?;
+                }
+            }
+            Command::Add { address, tickers } => {
+                f.write_fmt(format_args!("ADD udp://{} {}", address, tickers.join(",")))?;
             }
+            Command::Remove { address, tickers } => {
+                f.write_fmt(format_args!(
+                    "REMOVE udp://{} {}",
+                    address,
+                    tickers.join(",")
+                ))?;
+            }
+            Command::Unsubscribe { address, tickers } => {
+                f.write_fmt(format_args!("UNSUBSCRIBE udp://{}", address))?;
+                if !tickers.is_empty() {
+                    f.write_fmt(format_args!(" {}", tickers.join(",")))?;
+                }
+            }
+            Command::List => f.write_str("LIST")?,
+            Command::Snapshot { tickers } => {
+                f.write_str("SNAPSHOT")?;
+                if !tickers.is_empty() {
+                    f.write_fmt(format_args!(" {}", tickers.join(",")))?;
+                }
+            }
+            Command::History {
+                ticker,
+                from,
+                to,
+                limit,
+            } => {
+                f.write_fmt(format_args!("HISTORY {} {} {}", ticker, from, to))?;
+                if let Some(limit) = limit {
+                    f.write_fmt(format_args!(" {}", limit))?;
+                }
+            }
+            Command::Halt { ticker } => f.write_fmt(format_args!("HALT {}", ticker))?,
+            Command::Resume { ticker } => f.write_fmt(format_args!("RESUME {}", ticker))?,
+            Command::Event { event } => f.write_fmt(format_args!("EVENT {}", event))?,
+            Command::Auth { token } => f.write_fmt(format_args!("AUTH {}", token))?,
+            Command::Quit => f.write_str("QUIT")?,
         }
         Ok(())
     }
@@ -66,38 +254,79 @@ impl fmt::Display for Command {
 fn bad_request(s: &str) -> io::Error {
     io::Error::new(
         ErrorKind::InvalidInput,
-        format!(
-            "Bad request: [{}], (example 'STREAM udp://127.0.0.1:8080 TIC,TIC,TIC')",
-            s
-        ),
+        format!("Bad request: [{}], ({})", s, usage(s)),
     )
 }
 
+/// Usage of the command `s` starts with
+fn usage(s: &str) -> &'static str {
+    match s.split_whitespace().next().unwrap_or_default() {
+        "STREAM" => "usage 'STREAM udp://127.0.0.1:8080 TIC,TIC [trades,quotes,depth,prints]'",
+        "ADD" => "usage 'ADD udp://127.0.0.1:8080 TIC,TIC'",
+        "REMOVE" => "usage 'REMOVE udp://127.0.0.1:8080 TIC,TIC'",
+        "UNSUBSCRIBE" => "usage 'UNSUBSCRIBE udp://127.0.0.1:8080 [TIC,TIC]'",
+        "SNAPSHOT" => "usage 'SNAPSHOT [TIC,TIC]'",
+        "HISTORY" => "usage 'HISTORY TIC FROM TO [LIMIT]'",
+        "HALT" => "usage 'HALT TIC'",
+        "RESUME" => "usage 'RESUME TIC'",
+        "AUTH" => "usage 'AUTH TOKEN'",
+        _ => {
+            "commands: STREAM, ADD, REMOVE, UNSUBSCRIBE, LIST, SNAPSHOT, HISTORY, HALT, RESUME, \
+             EVENT, AUTH, QUIT"
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
 pub(crate) enum Response {
     Ok,
+    /// `OK` followed by one item per line and the `END` terminator
+    List(Vec<String>),
+    /// `WARN` line followed by `OK`
+    Warn(String),
     Err(String),
 }
 
+/// Command received over a control session, answered through `reply_tx`
+pub(crate) struct Request {
+    pub(crate) command: Command,
+    pub(crate) reply_tx: Sender<Response>,
+}
+
 impl fmt::Display for Response {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Response::Ok => write!(f, "OK\r\n")?,
+            Response::List(items) => {
+                write!(f, "OK\r\n")?;
+                for item in items {
+                    write!(f, "{}\r\n", item)?;
+                }
+                write!(f, "{}\r\n", END_OF_LIST)?;
+            }
+            Response::Warn(w) => write!(f, "WARN {}\r\nOK\r\n", w)?,
             Response::Err(e) => write!(f, "ERR {}\r\n", e)?,
         }
         Ok(())
     }
 }
 
-pub(crate) fn run(address: &str) -> io::Result<Receiver<Command>> {
+/// Accepts control sessions on `address`. Admin commands are refused unless `admin_token`
+/// is set and the session sent it with `AUTH`
+pub(crate) fn run(address: &str, admin_token: Option<String>) -> io::Result<Receiver<Request>> {
     let listener = TcpListener::bind(address)?;
-    let (tx, rx) = mpsc::channel();
+    let (tx, rx) = crossbeam::channel::unbounded();
+    let admin_token = Arc::new(admin_token);
     thread::spawn(move || {
-        for stream in listener.incoming() {
+        let owners = StreamOwners::default();
+        for (session, stream) in (1..).zip(listener.incoming()) {
             let tx = tx.clone();
+            let owners = owners.clone();
+            let admin_token = admin_token.clone();
             match stream {
                 Ok(stream) => {
                     thread::spawn(move || {
-                        handle_stream(tx, stream);
+                        handle_stream(tx, stream, session, owners, &admin_token);
                     });
                 }
                 Err(_) => {
@@ -110,55 +339,371 @@ pub(crate) fn run(address: &str) -> io::Result<Receiver<Command>> {
     Ok(rx)
 }
 
-fn handle_stream(tx: Sender<Command>, stream: TcpStream) {
-    let mut reader = BufReader::new(&stream);
-    let mut line = String::new();
-    let Ok(_) = reader.read_line(&mut line) else {
-        return;
-    };
+/// Serves commands line by line until `QUIT` or disconnect, then ends the streams
+/// started by this session and not restarted by another one since
+fn handle_stream(
+    tx: Sender<Request>,
+    stream: TcpStream,
+    session: u64,
+    owners: StreamOwners,
+    admin_token: &Option<String>,
+) {
     let Ok(mut writer) = stream.try_clone() else {
         return;
     };
-    match line.parse::<Command>() {
-        Ok(command) => {
-            let _ = tx.send(command);
-            let _ = writer.write_all(Response::Ok.to_string().as_bytes());
-            let _ = writer.flush();
+    let mut admin = false;
+    let peer = stream.peer_addr().ok();
+    let reader = BufReader::new(&stream);
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
         }
-        Err(e) => {
-            let _ = writer.write_all(Response::Err(e.to_string()).to_string().as_bytes());
-            let _ = writer.flush();
+        let (tag, line) = split_tag(&line);
+        let command = match line.parse::<Command>() {
+            Ok(command) => command,
+            Err(e) => {
+                if write_response(&mut writer, tag, Response::Err(e.to_string())).is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+        if command == Command::Quit {
+            let _ = write_response(&mut writer, tag, Response::Ok);
+            break;
+        }
+        if let Some(response) = authorize(&command, admin_token, &mut admin) {
+            if write_response(&mut writer, tag, response).is_err() {
+                break;
+            }
+            continue;
+        }
+        let session_change = match &command {
+            Command::Stream { address, .. } => Some((*address, true)),
+            Command::Unsubscribe { address, tickers } if tickers.is_empty() => {
+                Some((*address, false))
+            }
+            _ => None,
+        };
+        let response = match session_change {
+            // The lock is held while dispatching, so owners follow the order of the changes
+            Some((address, started)) => {
+                let mut owners = owners.lock();
+                let response = dispatch(&tx, command);
+                if matches!(response, Response::Ok) {
+                    if started {
+                        owners.insert(address, session);
+                    } else {
+                        owners.remove(&address);
+                    }
+                }
+                response
+            }
+            None => dispatch(&tx, command),
+        };
+        if write_response(&mut writer, tag, response).is_err() {
+            break;
         }
     }
+    let mut owners = owners.lock();
+    let streams: Vec<SocketAddr> = owners
+        .iter()
+        .filter(|(_, owner)| **owner == session)
+        .map(|(address, _)| *address)
+        .collect();
+    for address in streams {
+        owners.remove(&address);
+        info!(
+            "Control session {:?} closed, ending stream to {}",
+            peer, address
+        );
+        let _ = dispatch(
+            &tx,
+            Command::Unsubscribe {
+                address,
+                tickers: vec![],
+            },
+        );
+    }
+}
+
+/// Answers `AUTH` and admin commands the session may not send, other commands are
+/// left to dispatch
+fn authorize(
+    command: &Command,
+    admin_token: &Option<String>,
+    admin: &mut bool,
+) -> Option<Response> {
+    match command {
+        Command::Auth { token } => Some(match admin_token {
+            None => Response::Err("ADMIN_DISABLED".to_string()),
+            Some(expected) if expected == token => {
+                *admin = true;
+                Response::Ok
+            }
+            Some(_) => Response::Err("INVALID_TOKEN".to_string()),
+        }),
+        command if !command.is_admin() || *admin => None,
+        _ if admin_token.is_none() => Some(Response::Err("ADMIN_DISABLED".to_string())),
+        _ => Some(Response::Err("UNAUTHORIZED".to_string())),
+    }
+}
+
+fn dispatch(tx: &Sender<Request>, command: Command) -> Response {
+    let (reply_tx, reply_rx) = crossbeam::channel::bounded(1);
+    if tx.send(Request { command, reply_tx }).is_err() {
+        return Response::Err("Server is shutting down".to_string());
+    }
+    reply_rx
+        .recv()
+        .unwrap_or_else(|_| Response::Err("Server is shutting down".to_string()))
+}
+
+/// Splits an optional `#<tag>` correlation prefix off the command line
+fn split_tag(line: &str) -> (Option<&str>, &str) {
+    let line = line.trim();
+    match line.strip_prefix(TAG_PREFIX) {
+        Some(rest) => match rest.split_once(char::is_whitespace) {
+            Some((tag, command)) => (Some(tag), command),
+            None => (Some(rest), ""),
+        },
+        None => (None, line),
+    }
+}
+
+/// Writes `response`, every line of it prefixed with the tag of the command
+fn write_response(writer: &mut TcpStream, tag: Option<&str>, response: Response) -> io::Result<()> {
+    let response = response.to_string();
+    match tag {
+        Some(tag) => {
+            for line in response.split_inclusive("\r\n") {
+                write!(writer, "{}{} {}", TAG_PREFIX, tag, line)?;
+            }
+        }
+        None => writer.write_all(response.as_bytes())?,
+    }
+    writer.flush()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use rand::Rng;
+    use std::time::Duration;
 
     #[test]
     fn test_serialization_deserialization() {
         let command = Command::Stream {
             address: SocketAddr::from_str("127.0.0.1:8080").unwrap(),
             tickers: vec!["AAPL".to_string()],
+            kind: StreamKind::TRADES,
         };
         let value = command.to_string();
         let result = value.parse::<Command>().unwrap();
         assert_eq!(result, command);
     }
 
+    #[test]
+    fn test_stream_kind() {
+        let command = "STREAM udp://127.0.0.1:8080 AAPL both"
+            .parse::<Command>()
+            .unwrap();
+        let Command::Stream { kind, .. } = &command else {
+            panic!("stream expected, got {:?}", command);
+        };
+        assert_eq!(*kind, "trades,quotes".parse::<StreamKind>().unwrap());
+        assert_eq!(
+            command.to_string(),
+            "STREAM udp://127.0.0.1:8080 AAPL trades,quotes"
+        );
+        let command = "STREAM udp://127.0.0.1:8080 AAPL trades,depth,prints"
+            .parse::<Command>()
+            .unwrap();
+        assert_eq!(command.to_string().parse::<Command>().unwrap(), command);
+        assert!(
+            "STREAM udp://127.0.0.1:8080 AAPL levels"
+                .parse::<Command>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_add_remove_serialization_deserialization() {
+        let address = SocketAddr::from_str("127.0.0.1:8080").unwrap();
+        let tickers = vec!["AAPL".to_string(), "NFLX".to_string()];
+        for command in [
+            Command::Add {
+                address,
+                tickers: tickers.clone(),
+            },
+            Command::Remove { address, tickers },
+        ] {
+            let result = command.to_string().parse::<Command>().unwrap();
+            assert_eq!(result, command);
+        }
+        assert!("ADD udp://127.0.0.1:8080".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_empty_tickers_rejected() {
+        for command in [
+            "STREAM udp://127.0.0.1:8080 ,AAPL,,",
+            "ADD udp://127.0.0.1:8080 AAPL,",
+            "UNSUBSCRIBE udp://127.0.0.1:8080 ,",
+            "SNAPSHOT AAPL,,MSFT",
+        ] {
+            assert!(command.parse::<Command>().is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_list_response() {
+        assert_eq!("LIST".parse::<Command>().unwrap(), Command::List);
+        let response = Response::List(vec!["AAPL".to_string(), "NFLX".to_string()]);
+        assert_eq!(response.to_string(), "OK\r\nAAPL\r\nNFLX\r\nEND\r\n");
+    }
+
+    #[test]
+    fn test_snapshot_serialization_deserialization() {
+        for tickers in [vec![], vec!["AAPL".to_string(), "NFLX".to_string()]] {
+            let command = Command::Snapshot { tickers };
+            let result = command.to_string().parse::<Command>().unwrap();
+            assert_eq!(result, command);
+        }
+    }
+
+    #[test]
+    fn test_history_serialization_deserialization() {
+        for limit in [None, Some(10)] {
+            let command = Command::History {
+                ticker: "AAPL".to_string(),
+                from: 1000,
+                to: 2000,
+                limit,
+            };
+            let result = command.to_string().parse::<Command>().unwrap();
+            assert_eq!(result, command);
+        }
+        assert!("HISTORY AAPL 1000".parse::<Command>().is_err());
+        assert!("HISTORY AAPL 1000 now".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_halt_resume_serialization_deserialization() {
+        let ticker = "AAPL".to_string();
+        for command in [
+            Command::Halt {
+                ticker: ticker.clone(),
+            },
+            Command::Resume { ticker },
+        ] {
+            let result = command.to_string().parse::<Command>().unwrap();
+            assert_eq!(result, command);
+        }
+        assert!("HALT".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_event_serialization_deserialization() {
+        let command = "EVENT +30s NVDA drop 15%".parse::<Command>().unwrap();
+        assert_eq!(
+            command,
+            Command::Event {
+                event: "+30s NVDA drop 15%".parse().unwrap()
+            }
+        );
+        assert_eq!(command.to_string(), "EVENT +30s NVDA drop 15%");
+        assert!("EVENT NVDA".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_bad_request_usage() {
+        let error = |s: &str| s.parse::<Command>().unwrap_err().to_string();
+        assert!(error("HISTORY AAPL 1000").contains("'HISTORY TIC FROM TO [LIMIT]'"));
+        assert!(error("HALT").contains("'HALT TIC'"));
+        assert!(error("ADD udp://127.0.0.1:8080").contains("'ADD udp://127.0.0.1:8080 TIC,TIC'"));
+        assert!(error("PLAY AAPL").contains("commands: STREAM"));
+    }
+
+    #[test]
+    fn test_admin_commands_require_token() {
+        let port: i32 = rand::rng().random_range(8000..9000);
+        let address = format!("127.0.0.1:{}", port);
+        let rx = run(&address, Some("secret".to_string())).unwrap();
+        thread::spawn(move || {
+            while let Ok(request) = rx.recv() {
+                let _ = request.reply_tx.send(Response::Ok);
+            }
+        });
+
+        let mut stream = TcpStream::connect(&address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut exchange = |command: &str| {
+            stream
+                .write_all(format!("{}\r\n", command).as_bytes())
+                .unwrap();
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            line
+        };
+        assert_eq!(exchange("HALT AAPL"), "ERR UNAUTHORIZED\r\n");
+        assert_eq!(exchange("LIST"), "OK\r\n");
+        assert_eq!(exchange("AUTH guess"), "ERR INVALID_TOKEN\r\n");
+        assert_eq!(exchange("AUTH secret"), "OK\r\n");
+        assert_eq!(exchange("HALT AAPL"), "OK\r\n");
+    }
+
+    #[test]
+    fn test_admin_commands_disabled_without_token() {
+        let port: i32 = rand::rng().random_range(8000..9000);
+        let address = format!("127.0.0.1:{}", port);
+        let _rx = run(&address, None).unwrap();
+
+        let mut stream = TcpStream::connect(&address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        stream.write_all(b"EVENT NVDA drop 15%\r\n").unwrap();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "ERR ADMIN_DISABLED\r\n");
+        line.clear();
+        stream.write_all(b"AUTH secret\r\n").unwrap();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "ERR ADMIN_DISABLED\r\n");
+    }
+
+    #[test]
+    fn test_unsubscribe_serialization_deserialization() {
+        let address = SocketAddr::from_str("127.0.0.1:8080").unwrap();
+        for tickers in [vec![], vec!["AAPL".to_string(), "NFLX".to_string()]] {
+            let command = Command::Unsubscribe { address, tickers };
+            let result = command.to_string().parse::<Command>().unwrap();
+            assert_eq!(result, command);
+        }
+        assert_eq!(
+            Command::Unsubscribe {
+                address,
+                tickers: vec![]
+            }
+            .to_string(),
+            "UNSUBSCRIBE udp://127.0.0.1:8080"
+        );
+    }
+
     #[test]
     fn test_run_receive_command() {
         let port: i32 = rand::rng().random_range(8000..9000);
         let address = format!("127.0.0.1:{}", port);
 
-        let rx = run(&address).unwrap();
+        let rx = run(&address, None).unwrap();
 
         let tickers = vec!["AAPL".to_string()];
         let command = Command::Stream {
             address: SocketAddr::from_str("127.0.0.1:8080").unwrap(),
             tickers,
+            kind: StreamKind::TRADES,
         };
 
         let mut stream = TcpStream::connect(&address).unwrap();
@@ -168,6 +713,149 @@ mod tests {
 
         let result = rx.recv().unwrap();
 
-        assert_eq!(command, result);
+        assert_eq!(command, result.command);
+    }
+
+    #[test]
+    fn test_session_keeps_connection_open() {
+        let port: i32 = rand::rng().random_range(8000..9000);
+        let address = format!("127.0.0.1:{}", port);
+        let rx = run(&address, None).unwrap();
+        thread::spawn(move || {
+            while let Ok(request) = rx.recv() {
+                let response = match request.command {
+                    Command::Stream { .. } => Response::Ok,
+                    _ => Response::Err("Unknown subscriber".to_string()),
+                };
+                let _ = request.reply_tx.send(response);
+            }
+        });
+
+        let mut stream = TcpStream::connect(&address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+
+        stream
+            .write_all(b"#1 STREAM udp://127.0.0.1:8080 AAPL\r\n")
+            .unwrap();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "#1 OK\r\n");
+
+        line.clear();
+        stream
+            .write_all(b"REMOVE udp://127.0.0.1:9090 AAPL\r\n")
+            .unwrap();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "ERR Unknown subscriber\r\n");
+
+        line.clear();
+        stream.write_all(b"#q QUIT\r\n").unwrap();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "#q OK\r\n");
+
+        line.clear();
+        assert_eq!(reader.read_line(&mut line).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_session_close_ends_its_streams() {
+        let port: i32 = rand::rng().random_range(8000..9000);
+        let address = format!("127.0.0.1:{}", port);
+        let rx = run(&address, None).unwrap();
+
+        let udp_address = SocketAddr::from_str("127.0.0.1:8080").unwrap();
+        let stream_command = Command::Stream {
+            address: udp_address,
+            tickers: vec!["AAPL".to_string()],
+            kind: StreamKind::TRADES,
+        };
+        let mut stream = TcpStream::connect(&address).unwrap();
+        stream
+            .write_all(format!("{}\r\n", stream_command).as_bytes())
+            .unwrap();
+
+        let request = rx.recv().unwrap();
+        assert_eq!(request.command, stream_command);
+        request.reply_tx.send(Response::Ok).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        drop(reader);
+        drop(stream);
+
+        let request = rx.recv().unwrap();
+        assert_eq!(
+            request.command,
+            Command::Unsubscribe {
+                address: udp_address,
+                tickers: vec![]
+            }
+        );
+    }
+
+    #[test]
+    fn test_tagged_list_response() {
+        let port: i32 = rand::rng().random_range(8000..9000);
+        let address = format!("127.0.0.1:{}", port);
+        let rx = run(&address, None).unwrap();
+        thread::spawn(move || {
+            while let Ok(request) = rx.recv() {
+                let items = vec!["AAPL".to_string(), "MSFT".to_string()];
+                let _ = request.reply_tx.send(Response::List(items));
+            }
+        });
+
+        let mut stream = TcpStream::connect(&address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        stream.write_all(b"#7 LIST\r\n").unwrap();
+        let lines: Vec<String> = (0..4)
+            .map(|_| {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                line
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            ["#7 OK\r\n", "#7 AAPL\r\n", "#7 MSFT\r\n", "#7 END\r\n"]
+        );
+    }
+
+    #[test]
+    fn test_session_close_keeps_restarted_streams() {
+        let port: i32 = rand::rng().random_range(8000..9000);
+        let address = format!("127.0.0.1:{}", port);
+        let rx = run(&address, None).unwrap();
+        let udp_address = SocketAddr::from_str("127.0.0.1:8080").unwrap();
+        let stream_command = Command::Stream {
+            address: udp_address,
+            tickers: vec!["AAPL".to_string()],
+            kind: StreamKind::TRADES,
+        };
+        let start = |rx: &Receiver<Request>| {
+            let stream = TcpStream::connect(&address).unwrap();
+            (&stream)
+                .write_all(format!("{}\r\n", stream_command).as_bytes())
+                .unwrap();
+            let request = rx.recv().unwrap();
+            request.reply_tx.send(Response::Ok).unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            stream
+        };
+        let first = start(&rx);
+        let second = start(&rx);
+
+        drop(first);
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+        drop(second);
+        let request = rx.recv().unwrap();
+        assert_eq!(
+            request.command,
+            Command::Unsubscribe {
+                address: udp_address,
+                tickers: vec![]
+            }
+        );
     }
 }
@@ -0,0 +1,73 @@
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+#[derive(Default)]
+struct Names {
+    current: Vec<String>,
+    /// Symbols renamed away, their history is still stored under them
+    former: Vec<String>,
+}
+
+/// Tickers published by the source, in their original order, kept current through renames
+#[derive(Clone, Default)]
+pub(crate) struct Tickers {
+    names: Arc<RwLock<Names>>,
+}
+
+impl Tickers {
+    pub(crate) fn new(names: Vec<String>) -> Self {
+        Self {
+            names: Arc::new(RwLock::new(Names {
+                current: names,
+                former: vec![],
+            })),
+        }
+    }
+
+    pub(crate) fn contains(&self, ticker: &str) -> bool {
+        self.names.read().current.iter().any(|name| name == ticker)
+    }
+
+    /// Current tickers and the ones renamed away
+    pub(crate) fn has_history(&self, ticker: &str) -> bool {
+        let names = self.names.read();
+        names
+            .current
+            .iter()
+            .chain(&names.former)
+            .any(|name| name == ticker)
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.names.read().current.clone()
+    }
+
+    /// Lists `ticker` as `symbol` from now on
+    pub(crate) fn rename(&self, ticker: &str, symbol: &str) {
+        let mut names = self.names.write();
+        let Some(name) = names.current.iter_mut().find(|name| *name == ticker) else {
+            return;
+        };
+        *name = symbol.to_string();
+        names.former.retain(|name| name != symbol);
+        names.former.push(ticker.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_keeps_history() {
+        let tickers = Tickers::new(vec!["FB".to_string(), "AAPL".to_string()]);
+        tickers.rename("FB", "META");
+        assert_eq!(
+            tickers.names(),
+            vec!["META".to_string(), "AAPL".to_string()]
+        );
+        assert!(!tickers.contains("FB"));
+        assert!(tickers.has_history("FB") && tickers.has_history("META"));
+        assert!(!tickers.has_history("MSFT"));
+    }
+}
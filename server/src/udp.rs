@@ -1,52 +1,433 @@
-use crate::tcp::Command;
-use crate::udp::client::Client;
+use crate::cache::QuoteCache;
+use crate::generator::events::MarketEvents;
+use crate::generator::session::TradingHalts;
+use crate::history::QuoteHistory;
+use crate::journal::JournalReader;
+use crate::metrics::StopReason;
+use crate::tcp::{Command, Request, Response};
+use crate::tickers::Tickers;
+use crate::udp::hub::Hub;
+use crate::udp::registry::ClientRegistry;
+use clap::ValueEnum;
 use crossbeam::channel::{Receiver, Sender};
-use log::error;
+use crossbeam::select;
+use log::{error, info};
+use quotes::{StockQuote, StreamKind};
 use std::io;
 use std::net::{SocketAddr, UdpSocket};
 use std::process::exit;
 use std::sync::mpsc;
 
 mod client;
+mod hub;
 mod monitor;
+mod registry;
+#[cfg(test)]
+mod test_helpers;
 
 pub use crate::udp::client::ClientCommand;
+pub(crate) use crate::udp::hub::QuoteSink;
 use crate::udp::monitor::ClientsMonitor;
 
+/// What to do with requested tickers the generator never produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum UnknownTickers {
+    /// Answer `ERR UNKNOWN_TICKER`
+    Reject,
+    /// Ignore them and answer `WARN UNKNOWN_TICKER`
+    Warn,
+}
+
+/// Server state available to control commands
+pub(crate) struct Context {
+    pub(crate) tickers: Tickers,
+    pub(crate) unknown_tickers: UnknownTickers,
+    pub(crate) cache: QuoteCache,
+    pub(crate) history: QuoteHistory,
+    /// Reader of the journal, HISTORY is served from it when set
+    pub(crate) journal: Option<JournalReader>,
+    /// Halts of the generator, `None` for sources without trading sessions
+    pub(crate) halts: Option<TradingHalts>,
+    /// Scripted events of the generator, `None` for other sources
+    pub(crate) events: Option<MarketEvents>,
+    /// Market data the quote source publishes
+    pub(crate) published: StreamKind,
+}
+
 pub(crate) fn run(
     port: u16,
-    command_rx: mpsc::Receiver<Command>,
-    tx: Sender<ClientCommand>,
+    context: Context,
+    mut sinks: Vec<Box<dyn QuoteSink>>,
+    command_rx: Receiver<Request>,
     rx: Receiver<ClientCommand>,
+    shutdown_rx: Receiver<()>,
 ) -> io::Result<()> {
     let address = format!("127.0.0.1:{}", port);
     let socket = UdpSocket::bind(address)?;
 
-    let new_client_tx = ClientsMonitor::run(socket.try_clone()?, tx);
+    sinks.push(Box::new(context.cache.clone()));
+    sinks.push(Box::new(context.history.clone()));
+    let registry = ClientRegistry::new(Hub::run(rx, sinks));
+    let new_client_tx = ClientsMonitor::run(socket.try_clone()?, registry.clone());
+    let dispatcher = Dispatcher {
+        socket,
+        context,
+        registry,
+        new_client_tx,
+    };
 
-    while let Ok(command) = command_rx.recv() {
-        let rx = rx.clone();
-        handle_command(socket.try_clone()?, command, rx, new_client_tx.clone());
+    loop {
+        select! {
+            recv(command_rx) -> command => {
+                let Ok(request) = command else {
+                    break;
+                };
+                dispatcher.serve(request)?;
+            }
+            recv(shutdown_rx) -> _ => {
+                info!("Shutting down");
+                break;
+            }
+        }
     }
+    dispatcher.registry.stop_all(StopReason::Shutdown);
 
     Ok(())
 }
 
-fn handle_command(
+/// Executes control commands against the running subscribers
+struct Dispatcher {
     socket: UdpSocket,
-    command: Command,
-    rx: Receiver<ClientCommand>,
+    context: Context,
+    registry: ClientRegistry,
     new_client_tx: mpsc::Sender<SocketAddr>,
-) {
-    match command {
-        Command::Stream { address, tickers } => {
-            if let Err(e) = new_client_tx.send(address) {
-                error!("Failed to send client address: {}", e);
-                exit(-1);
+}
+
+impl Dispatcher {
+    /// Answers `request`, HISTORY is answered by the journal reader when there is one
+    fn serve(&self, request: Request) -> io::Result<()> {
+        let Request { command, reply_tx } = request;
+        match (command, &self.context.journal) {
+            (
+                Command::History {
+                    ticker,
+                    from,
+                    to,
+                    limit,
+                },
+                Some(journal),
+            ) if self.context.tickers.has_history(&ticker) => {
+                read_journal(journal, &ticker, from, to, limit, reply_tx);
             }
-            if let Err(e) = Client::run(socket, address, tickers.into_iter().collect(), rx) {
-                error!("{}", e);
+            (command, _) => {
+                let _ = reply_tx.send(self.handle(command)?);
             }
         }
+        Ok(())
+    }
+
+    /// Answers `command` right away, HISTORY from memory
+    fn handle(&self, command: Command) -> io::Result<Response> {
+        let response = match command {
+            Command::Stream {
+                address,
+                tickers,
+                kind,
+            } => {
+                if let Some(unsupported) = unpublished(kind, self.context.published) {
+                    return Ok(Response::Err(format!("UNSUPPORTED_STREAM {}", unsupported)));
+                }
+                let (tickers, warning) = match self.check_tickers(tickers) {
+                    Ok(checked) => checked,
+                    Err(response) => return Ok(response),
+                };
+                if let Err(e) = self.new_client_tx.send(address) {
+                    error!("Failed to send client address: {}", e);
+                    exit(-1);
+                }
+                let socket = self.socket.try_clone()?;
+                match self
+                    .registry
+                    .start(socket, address, tickers.into_iter().collect(), kind)
+                {
+                    Ok(()) => warning.unwrap_or(Response::Ok),
+                    Err(e) => {
+                        error!("{}", e);
+                        Response::Err(e.to_string())
+                    }
+                }
+            }
+            Command::Add { address, tickers } => {
+                let (tickers, warning) = match self.check_tickers(tickers) {
+                    Ok(checked) => checked,
+                    Err(response) => return Ok(response),
+                };
+                match subscriber_response(
+                    address,
+                    self.registry
+                        .send(address, ClientCommand::Subscribe(tickers)),
+                ) {
+                    Response::Ok => warning.unwrap_or(Response::Ok),
+                    response => response,
+                }
+            }
+            Command::Remove { address, tickers } => subscriber_response(
+                address,
+                self.registry
+                    .send(address, ClientCommand::Unsubscribe(tickers)),
+            ),
+            Command::Unsubscribe { address, tickers } => {
+                let found = if tickers.is_empty() {
+                    self.registry.stop(address, StopReason::Unsubscribe)
+                } else {
+                    self.registry
+                        .send(address, ClientCommand::Unsubscribe(tickers))
+                };
+                subscriber_response(address, found)
+            }
+            Command::List => Response::List(self.context.tickers.names()),
+            Command::Snapshot { tickers } => json_lines(self.context.cache.snapshot(&tickers))?,
+            Command::History {
+                ticker,
+                from,
+                to,
+                limit,
+            } => {
+                if !self.context.tickers.has_history(&ticker) {
+                    return Ok(Response::Err(format!("UNKNOWN_TICKER {}", ticker)));
+                }
+                json_lines(self.context.history.query(&ticker, from, to, limit))?
+            }
+            Command::Halt { ticker } => self.update_halts(&ticker, TradingHalts::halt),
+            Command::Resume { ticker } => self.update_halts(&ticker, TradingHalts::resume),
+            Command::Event { event } => match &self.context.events {
+                Some(events) => match events.inject(event) {
+                    Ok(()) => Response::Ok,
+                    Err(e) => Response::Err(e),
+                },
+                None => Response::Err("Events are not supported by the quote source".to_string()),
+            },
+            Command::Auth { .. } | Command::Quit => Response::Ok,
+        };
+        Ok(response)
+    }
+
+    fn update_halts(&self, ticker: &str, update: fn(&TradingHalts, &str)) -> Response {
+        let Some(halts) = &self.context.halts else {
+            return Response::Err("Halts are not supported by the quote source".to_string());
+        };
+        if !self.context.tickers.contains(ticker) {
+            return Response::Err(format!("UNKNOWN_TICKER {}", ticker));
+        }
+        update(halts, ticker);
+        Response::Ok
+    }
+
+    /// Splits off tickers missing from the universe, according to `unknown_tickers` policy
+    fn check_tickers(
+        &self,
+        tickers: Vec<String>,
+    ) -> Result<(Vec<String>, Option<Response>), Response> {
+        let (known, unknown): (Vec<String>, Vec<String>) = tickers
+            .into_iter()
+            .partition(|ticker| self.context.tickers.contains(ticker));
+        if known.is_empty() && unknown.is_empty() {
+            return Err(Response::Err("No tickers requested".to_string()));
+        }
+        if unknown.is_empty() {
+            return Ok((known, None));
+        }
+        let message = format!("UNKNOWN_TICKER {}", unknown.join(","));
+        if known.is_empty() || self.context.unknown_tickers == UnknownTickers::Reject {
+            return Err(Response::Err(message));
+        }
+        Ok((known, Some(Response::Warn(message))))
+    }
+}
+
+/// Market data of `kind` the source never publishes, `None` if it publishes all of it
+fn unpublished(kind: StreamKind, published: StreamKind) -> Option<StreamKind> {
+    let missing = StreamKind {
+        trades: kind.trades && !published.trades,
+        quotes: kind.quotes && !published.quotes,
+        depth: kind.depth && !published.depth,
+        prints: kind.prints && !published.prints,
+    };
+    (missing.trades || missing.quotes || missing.depth || missing.prints).then_some(missing)
+}
+
+/// Queues a journal read answering `reply_tx` once done
+fn read_journal(
+    journal: &JournalReader,
+    ticker: &str,
+    from: u64,
+    to: u64,
+    limit: Option<usize>,
+    reply_tx: Sender<Response>,
+) {
+    let reader_tx = reply_tx.clone();
+    let queued = journal.read(ticker, from, to, limit, move |quotes| {
+        let response = quotes.and_then(json_lines).unwrap_or_else(|e| {
+            error!("Journal read failed: {}", e);
+            Response::Err(format!("Journal read failed: {}", e))
+        });
+        let _ = reader_tx.send(response);
+    });
+    if !queued {
+        let _ = reply_tx.send(Response::Err("JOURNAL_BUSY".to_string()));
+    }
+}
+
+fn json_lines(quotes: Vec<StockQuote>) -> io::Result<Response> {
+    let mut lines = Vec::with_capacity(quotes.len());
+    for quote in quotes {
+        lines.push(serde_json::to_string(&quote).map_err(io::Error::other)?);
+    }
+    Ok(Response::List(lines))
+}
+
+fn subscriber_response(address: SocketAddr, found: bool) -> Response {
+    if found {
+        Response::Ok
+    } else {
+        Response::Err(format!("Unknown subscriber udp://{}", address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispatcher(unknown_tickers: UnknownTickers) -> Dispatcher {
+        let (_, rx) = crossbeam::channel::bounded(1);
+        Dispatcher {
+            socket: UdpSocket::bind("127.0.0.1:0").unwrap(),
+            context: Context {
+                tickers: Tickers::new(vec!["AAPL".to_string(), "NFLX".to_string()]),
+                unknown_tickers,
+                cache: QuoteCache::new(),
+                history: QuoteHistory::new(0, None),
+                journal: None,
+                halts: Some(TradingHalts::new()),
+                events: None,
+                published: StreamKind::TRADES,
+            },
+            registry: ClientRegistry::new(Hub::run(rx, vec![])),
+            new_client_tx: mpsc::channel().0,
+        }
+    }
+
+    #[test]
+    fn test_unpublished_stream() {
+        let dispatcher = dispatcher(UnknownTickers::Warn);
+        let response = dispatcher
+            .handle(Command::Stream {
+                address: SocketAddr::from(([127, 0, 0, 1], 9461)),
+                tickers: vec!["AAPL".to_string()],
+                kind: "both,prints".parse().unwrap(),
+            })
+            .unwrap();
+        assert_eq!(
+            response,
+            Response::Err("UNSUPPORTED_STREAM quotes,prints".to_string())
+        );
+        assert_eq!(unpublished(StreamKind::TRADES, StreamKind::TRADES), None);
+    }
+
+    #[test]
+    fn test_halt_unknown_ticker() {
+        let dispatcher = dispatcher(UnknownTickers::Warn);
+        let response = dispatcher
+            .handle(Command::Halt {
+                ticker: "APPL".to_string(),
+            })
+            .unwrap();
+        assert_eq!(response, Response::Err("UNKNOWN_TICKER APPL".to_string()));
+        let response = dispatcher
+            .handle(Command::Halt {
+                ticker: "AAPL".to_string(),
+            })
+            .unwrap();
+        assert_eq!(response, Response::Ok);
+        let halts = dispatcher.context.halts.as_ref().unwrap();
+        assert!(halts.halted().contains("AAPL"));
+    }
+
+    #[test]
+    fn test_unknown_tickers_rejected() {
+        let dispatcher = dispatcher(UnknownTickers::Reject);
+        let result = dispatcher.check_tickers(vec!["AAPL".to_string(), "APPL".to_string()]);
+        assert_eq!(
+            result,
+            Err(Response::Err("UNKNOWN_TICKER APPL".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_unknown_tickers_warned() {
+        let dispatcher = dispatcher(UnknownTickers::Warn);
+        let result = dispatcher.check_tickers(vec!["AAPL".to_string(), "APPL".to_string()]);
+        assert_eq!(
+            result,
+            Ok((
+                vec!["AAPL".to_string()],
+                Some(Response::Warn("UNKNOWN_TICKER APPL".to_string()))
+            ))
+        );
+        let result = dispatcher.check_tickers(vec!["APPL".to_string()]);
+        assert_eq!(
+            result,
+            Err(Response::Err("UNKNOWN_TICKER APPL".to_string()))
+        );
+        assert!(dispatcher.check_tickers(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_history_of_renamed_ticker() {
+        let mut dispatcher = dispatcher(UnknownTickers::Warn);
+        dispatcher.context.history = QuoteHistory::new(10, None);
+        let quote = StockQuote::new("NFLX", quotes::Price::from_cents(100), 10);
+        dispatcher.context.history.record(&quote);
+        dispatcher.context.tickers.rename("NFLX", "NFX");
+        let history = |ticker: &str| {
+            dispatcher
+                .handle(Command::History {
+                    ticker: ticker.to_string(),
+                    from: 0,
+                    to: u64::MAX,
+                    limit: None,
+                })
+                .unwrap()
+        };
+        assert_eq!(
+            history("NFLX"),
+            Response::List(vec![serde_json::to_string(&quote).unwrap()])
+        );
+        assert_eq!(history("NFX"), Response::List(vec![]));
+        assert_eq!(
+            history("MSFT"),
+            Response::Err("UNKNOWN_TICKER MSFT".to_string())
+        );
+    }
+
+    #[test]
+    fn test_journal_read_error() {
+        let mut dispatcher = dispatcher(UnknownTickers::Warn);
+        let missing = std::env::temp_dir().join(format!("journal-missing-{}", std::process::id()));
+        dispatcher.context.journal = Some(JournalReader::run(missing));
+        let (reply_tx, reply_rx) = crossbeam::channel::bounded(1);
+        let command = Command::History {
+            ticker: "AAPL".to_string(),
+            from: 0,
+            to: u64::MAX,
+            limit: None,
+        };
+        dispatcher.serve(Request { command, reply_tx }).unwrap();
+        let response = reply_rx.recv().unwrap();
+        assert!(
+            matches!(&response, Response::Err(e) if e.starts_with("Journal read failed")),
+            "{}",
+            response
+        );
     }
 }
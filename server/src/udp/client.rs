@@ -1,22 +1,47 @@
+use crate::metrics::StopReason;
 use crossbeam::channel::Receiver;
+use crossbeam::select;
 use log::{error, info};
-use quotes::StockQuote;
+use quotes::{
+    BookQuote, CorporateAction, CorporateActionKind, DepthSnapshot, DepthUpdate, Envelope,
+    MarketEvent, MarketStatus, Message, StockQuote, StreamKind, Trade,
+};
 use std::collections::HashSet;
 use std::net::{SocketAddr, UdpSocket};
+use std::thread::JoinHandle;
 use std::time::SystemTime;
 use std::{io, thread};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ClientCommand {
     Send(StockQuote),
-    Stop(SocketAddr),
+    /// Trade print, sent to streams of prints
+    Trade(Trade),
+    /// Top of the book, sent to streams of quotes
+    Book(BookQuote),
+    /// Incremental depth update, sent to streams of depth
+    Depth(DepthUpdate),
+    /// Full depth, sent to streams of depth
+    DepthSnapshot(DepthSnapshot),
+    /// Session or halt state change, sent to every subscriber of the ticker
+    Status(MarketStatus),
+    /// Corporate action, a rename moves subscriptions to the new symbol
+    Action(CorporateAction),
+    Replace(HashSet<String>, StreamKind),
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+    Stop(StopReason),
 }
 
 pub(crate) struct Client {
     socket: UdpSocket,
     address: SocketAddr,
     tickers: HashSet<String>,
+    kind: StreamKind,
+    /// Sequence of the last envelope sent
+    sequence: u64,
     stock_rx: Receiver<ClientCommand>,
+    control_rx: Receiver<ClientCommand>,
 }
 
 impl Client {
@@ -24,98 +49,264 @@ impl Client {
         socket: UdpSocket,
         address: SocketAddr,
         tickers: HashSet<String>,
+        kind: StreamKind,
         stock_rx: Receiver<ClientCommand>,
-    ) -> io::Result<()> {
-        let client = Client::new(socket, address, tickers, stock_rx)?;
-        thread::spawn(move || {
+        control_rx: Receiver<ClientCommand>,
+    ) -> io::Result<JoinHandle<()>> {
+        let mut client = Client::new(socket, address, tickers, kind, stock_rx, control_rx)?;
+        Ok(thread::spawn(move || {
             client.start().expect("Client error");
-        });
-        Ok(())
+        }))
     }
 
     fn new(
         socket: UdpSocket,
         address: SocketAddr,
         tickers: HashSet<String>,
+        kind: StreamKind,
         stock_rx: Receiver<ClientCommand>,
+        control_rx: Receiver<ClientCommand>,
     ) -> io::Result<Self> {
         Ok(Self {
             socket,
             address,
             tickers,
+            kind,
+            sequence: 0,
             stock_rx,
+            control_rx,
         })
     }
 
-    fn start(&self) -> io::Result<()> {
+    fn start(&mut self) -> io::Result<()> {
         let start_timestamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .map_err(io::Error::other)?
             .as_secs();
-        while let Ok(command) = self.stock_rx.recv() {
+        loop {
+            let command = select! {
+                recv(self.control_rx) -> command => command,
+                recv(self.stock_rx) -> command => command,
+            };
+            let Ok(command) = command else {
+                break;
+            };
             match command {
                 ClientCommand::Send(stock) => {
                     if start_timestamp > stock.timestamp {
                         continue;
                     }
-                    if self.tickers.contains(&stock.ticker) {
-                        match serde_json::to_vec(&stock) {
-                            Ok(stock) => {
-                                if let Err(e) = self.socket.send_to(&stock, self.address) {
-                                    error!("Failed to send stock: {}", e);
-                                    break;
-                                }
-                            }
-                            Err(e) => error!("JSON error: {}", e),
-                        }
+                    if self.kind.trades
+                        && self.tickers.contains(&stock.ticker)
+                        && !self.send(MarketEvent::Quote(stock), "stock")
+                    {
+                        break;
+                    }
+                }
+                ClientCommand::Trade(trade) => {
+                    if start_timestamp > trade.timestamp {
+                        continue;
+                    }
+                    if self.kind.prints
+                        && self.tickers.contains(&trade.ticker)
+                        && !self.send(MarketEvent::Trade(trade), "trade")
+                    {
+                        break;
+                    }
+                }
+                ClientCommand::Book(book) => {
+                    if start_timestamp > book.timestamp {
+                        continue;
+                    }
+                    if self.kind.quotes
+                        && self.tickers.contains(&book.ticker)
+                        && !self.send(MarketEvent::Book(book), "book quote")
+                    {
+                        break;
                     }
                 }
-                ClientCommand::Stop(address) => {
-                    if address == self.address {
-                        info!("subscriber {} stopped", self.address);
+                ClientCommand::Depth(update) => {
+                    if self.kind.depth
+                        && self.tickers.contains(&update.ticker)
+                        && !self.send(MarketEvent::Depth(update), "depth update")
+                    {
                         break;
                     }
                 }
+                ClientCommand::DepthSnapshot(snapshot) => {
+                    if self.kind.depth
+                        && self.tickers.contains(&snapshot.ticker)
+                        && !self.send(MarketEvent::DepthSnapshot(snapshot), "depth snapshot")
+                    {
+                        break;
+                    }
+                }
+                ClientCommand::Status(status) => {
+                    let subscribed = status
+                        .ticker
+                        .as_ref()
+                        .is_none_or(|ticker| self.tickers.contains(ticker));
+                    if subscribed && !self.send(MarketEvent::Status(status), "status") {
+                        break;
+                    }
+                }
+                ClientCommand::Action(action) => {
+                    if !self.tickers.contains(&action.ticker) {
+                        continue;
+                    }
+                    if let CorporateActionKind::Rename { symbol } = &action.kind {
+                        self.tickers.remove(&action.ticker);
+                        self.tickers.insert(symbol.clone());
+                        info!(
+                            "subscriber {} moved from {} to {}",
+                            self.address, action.ticker, symbol
+                        );
+                    }
+                    if !self.send(MarketEvent::Action(action), "corporate action") {
+                        break;
+                    }
+                }
+                ClientCommand::Replace(tickers, kind) => {
+                    self.tickers = tickers;
+                    self.kind = kind;
+                    info!("subscriber {} tickers replaced", self.address);
+                }
+                ClientCommand::Subscribe(tickers) => {
+                    self.tickers.extend(tickers.iter().cloned());
+                    info!(
+                        "subscriber {} subscribed to: {}",
+                        self.address,
+                        tickers.join(",")
+                    );
+                }
+                ClientCommand::Unsubscribe(tickers) => {
+                    for ticker in &tickers {
+                        self.tickers.remove(ticker);
+                    }
+                    info!(
+                        "subscriber {} unsubscribed from: {}",
+                        self.address,
+                        tickers.join(",")
+                    );
+                }
+                ClientCommand::Stop(reason) => {
+                    info!("subscriber {} stopped: {}", self.address, reason);
+                    break;
+                }
             }
         }
         Ok(())
     }
+
+    /// Sends `event` in the next envelope, returns `false` once the socket fails
+    fn send(&mut self, event: MarketEvent, kind: &str) -> bool {
+        self.sequence += 1;
+        let envelope = Envelope::new(self.sequence, Message::Event(event));
+        match serde_json::to_vec(&envelope) {
+            Ok(message) => {
+                if let Err(e) = self.socket.send_to(&message, self.address) {
+                    error!("Failed to send {}: {}", kind, e);
+                    return false;
+                }
+            }
+            Err(e) => error!("JSON error: {}", e),
+        }
+        true
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::udp::test_helpers::{receive, receive_event};
     use crate::variables::CHANNEL_SIZE;
-    use rand::random_range;
-    use std::net::{IpAddr, Ipv4Addr};
+    use crossbeam::channel::Sender;
+    use quotes::Price;
     use std::time::Duration;
 
+    /// Runs a client streaming `kind` of `ticker` to a new socket, returns its data and
+    /// control queues and the socket
+    fn start(
+        ticker: &str,
+        kind: StreamKind,
+    ) -> (Sender<ClientCommand>, Sender<ClientCommand>, UdpSocket) {
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        let (tx, rx) = crossbeam::channel::bounded(CHANNEL_SIZE);
+        let (control_tx, control_rx) = crossbeam::channel::unbounded();
+        let tickers = HashSet::from([ticker.to_string()]);
+        let address = receiver.local_addr().unwrap();
+        Client::run(udp, address, tickers, kind, rx, control_rx).unwrap();
+        (tx, control_tx, receiver)
+    }
+
     #[test]
     fn test_send_command() {
-        let server = format!("127.0.0.1:{}", random_range::<i32, _>(8000..9000));
+        let (tx, _control_tx, client) = start("AAPL", StreamKind::TRADES);
 
-        let udp = UdpSocket::bind(&server).unwrap();
-        let (tx, rx) = crossbeam::channel::bounded(CHANNEL_SIZE);
+        let stock = StockQuote::new("AAPL", Price::from_cents(100), 100);
+        tx.send(ClientCommand::Send(stock.clone())).unwrap();
 
-        let client = SocketAddr::new(
-            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
-            random_range(8000..9000),
-        );
-        let mut tickers = HashSet::new();
-        tickers.insert(String::from("AAPL"));
-        Client::run(udp, client.clone(), tickers, rx).unwrap();
-        let client = UdpSocket::bind(client).unwrap();
+        let result = receive_event(&client);
 
-        thread::sleep(Duration::from_millis(100));
+        assert_eq!(result, MarketEvent::Quote(stock));
+    }
+
+    #[test]
+    fn test_rename_moves_subscription() {
+        let (tx, _control_tx, receiver) = start("FB", StreamKind::TRADES);
 
-        let stock = StockQuote::new("AAPL", 100, 100);
+        let rename = CorporateAction::rename("FB", "META", 1);
+        tx.send(ClientCommand::Action(rename.clone())).unwrap();
+        let stock = StockQuote::new("META", Price::from_cents(100), 100);
         tx.send(ClientCommand::Send(stock.clone())).unwrap();
 
+        let result = receive_event(&receiver);
+        assert_eq!(result, MarketEvent::Action(rename));
+        let result = receive_event(&receiver);
+        assert_eq!(result, MarketEvent::Quote(stock));
+    }
+
+    #[test]
+    fn test_quotes_stream_skips_trades() {
+        let (tx, _control_tx, receiver) = start("AAPL", "quotes".parse().unwrap());
+
+        let stock = StockQuote::new("AAPL", Price::from_cents(100), 100);
+        let (bid, ask) = (Price::from_cents(99), Price::from_cents(101));
+        let book = BookQuote::new("AAPL", bid, 200, ask, 300, stock.timestamp);
+        tx.send(ClientCommand::Send(stock)).unwrap();
+        tx.send(ClientCommand::Book(book.clone())).unwrap();
+
+        let result = receive_event(&receiver);
+        assert_eq!(result, MarketEvent::Book(book));
         let mut buffer = [0u8; 2048];
-        let len = client.recv(&mut buffer).unwrap();
+        assert!(receiver.recv(&mut buffer).is_err());
+    }
+
+    #[test]
+    fn test_prints_stream_sends_numbered_trades() {
+        let (tx, _control_tx, receiver) = start("AAPL", "trades,prints".parse().unwrap());
 
-        let result: StockQuote = serde_json::from_slice(&buffer[..len]).unwrap();
+        let stock = StockQuote::new("AAPL", Price::from_cents(100), 100);
+        let trade = Trade::new(
+            "AAPL",
+            1,
+            stock.price,
+            100,
+            quotes::Side::Bid,
+            stock.timestamp,
+        );
+        tx.send(ClientCommand::Send(stock.clone())).unwrap();
+        tx.send(ClientCommand::Trade(trade.clone())).unwrap();
 
-        assert_eq!(stock, result);
+        let first = receive(&receiver);
+        assert_eq!(first.sequence, 1);
+        assert_eq!(first.message, Message::Event(MarketEvent::Quote(stock)));
+        let second = receive(&receiver);
+        assert_eq!(second.sequence, 2);
+        assert_eq!(second.message, Message::Event(MarketEvent::Trade(trade)));
     }
 }
@@ -0,0 +1,191 @@
+use crate::metrics::METRICS;
+use crate::udp::client::ClientCommand;
+use crate::variables::CHANNEL_SIZE;
+use crossbeam::channel::{Receiver, Sender, TrySendError};
+use log::{debug, warn};
+use parking_lot::Mutex;
+use quotes::{CorporateAction, StockQuote};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread;
+
+/// Queues of one subscriber
+struct Subscriber {
+    /// Market data, dropped when full
+    tx: Sender<ClientCommand>,
+    /// Control queue of the worker, takes corporate actions the data queue has no room for
+    control_tx: Sender<ClientCommand>,
+}
+
+type Subscribers = Arc<Mutex<HashMap<SocketAddr, Subscriber>>>;
+
+/// Receives every published quote before it is fanned out to subscribers
+pub(crate) trait QuoteSink: Send {
+    fn record(&self, quote: &StockQuote);
+
+    /// Called for every corporate action before the quotes it affects
+    fn corporate_action(&self, _action: &CorporateAction) {}
+}
+
+/// Fans out every command from the generator to each subscriber's own queue
+#[derive(Clone)]
+pub(crate) struct Hub {
+    subscribers: Subscribers,
+}
+
+impl Hub {
+    pub(crate) fn run(rx: Receiver<ClientCommand>, sinks: Vec<Box<dyn QuoteSink>>) -> Self {
+        let hub = Self::new();
+        let fan_out = hub.clone();
+        thread::spawn(move || {
+            while let Ok(command) = rx.recv() {
+                match &command {
+                    ClientCommand::Send(stock) => {
+                        for sink in &sinks {
+                            sink.record(stock);
+                        }
+                    }
+                    ClientCommand::Action(action) => {
+                        for sink in &sinks {
+                            sink.corporate_action(action);
+                        }
+                    }
+                    _ => {}
+                }
+                fan_out.publish(command);
+            }
+        });
+        hub
+    }
+
+    fn new() -> Self {
+        Self {
+            subscribers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub(crate) fn subscribe(
+        &self,
+        address: SocketAddr,
+        control_tx: Sender<ClientCommand>,
+    ) -> Receiver<ClientCommand> {
+        let (tx, rx) = crossbeam::channel::bounded(CHANNEL_SIZE);
+        let subscriber = Subscriber { tx, control_tx };
+        if self
+            .subscribers
+            .lock()
+            .insert(address, subscriber)
+            .is_some()
+        {
+            debug!("Subscriber {} replaced", address);
+        }
+        rx
+    }
+
+    pub(crate) fn unsubscribe(&self, address: SocketAddr) {
+        self.subscribers.lock().remove(&address);
+    }
+
+    fn publish(&self, command: ClientCommand) {
+        let mut subscribers = self.subscribers.lock();
+        subscribers.retain(
+            |address, subscriber| match subscriber.tx.try_send(command.clone()) {
+                Ok(()) => true,
+                // A lost rename would leave the subscriber on the old symbol
+                Err(TrySendError::Full(command @ ClientCommand::Action(_))) => {
+                    warn!("Queue of {} is full, corporate action sent ahead", address);
+                    subscriber.control_tx.send(command).is_ok()
+                }
+                Err(TrySendError::Full(_)) => {
+                    warn!("Queue of {} is full, command dropped", address);
+                    METRICS.quote_dropped();
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    debug!("Subscriber {} disconnected", address);
+                    false
+                }
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::udp::registry::ClientRegistry;
+    use crate::udp::test_helpers::receive_event;
+    use quotes::Price;
+    use quotes::StreamKind;
+    use quotes::{MarketEvent, StockQuote};
+    use rand::random_range;
+    use std::collections::HashSet;
+    use std::net::UdpSocket;
+    use std::time::Duration;
+
+    const QUOTES_COUNT: usize = 64;
+
+    #[test]
+    fn test_every_subscriber_receives_every_quote() {
+        let (tx, rx) = crossbeam::channel::bounded(CHANNEL_SIZE);
+        let registry = ClientRegistry::new(Hub::run(rx, vec![]));
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let mut subscribers = Vec::new();
+        for _ in 0..2 {
+            let port = random_range(9000..10000);
+            let socket = UdpSocket::bind(format!("127.0.0.1:{}", port)).unwrap();
+            socket
+                .set_read_timeout(Some(Duration::from_secs(1)))
+                .unwrap();
+            let address = socket.local_addr().unwrap();
+            let tickers = HashSet::from([String::from("AAPL")]);
+            registry
+                .start(
+                    server.try_clone().unwrap(),
+                    address,
+                    tickers,
+                    StreamKind::TRADES,
+                )
+                .unwrap();
+            subscribers.push(socket);
+        }
+
+        let sent: Vec<StockQuote> = (0..QUOTES_COUNT as u64)
+            .map(|i| StockQuote::new("AAPL", Price::from_cents(100 + i), 1000))
+            .collect();
+        for stock in &sent {
+            tx.send(ClientCommand::Send(stock.clone())).unwrap();
+        }
+
+        for socket in subscribers {
+            let mut received = Vec::new();
+            while received.len() < QUOTES_COUNT {
+                received.push(receive_event(&socket));
+            }
+            let sent: Vec<MarketEvent> = sent.iter().cloned().map(MarketEvent::Quote).collect();
+            assert_eq!(sent, received);
+        }
+    }
+
+    #[test]
+    fn test_full_queue_keeps_corporate_actions() {
+        let hub = Hub::new();
+        let address = "127.0.0.1:9999".parse().unwrap();
+        let (control_tx, control_rx) = crossbeam::channel::unbounded();
+        let stock_rx = hub.subscribe(address, control_tx);
+        let stock = StockQuote::new("FB", Price::from_cents(100), 1000);
+        for _ in 0..=CHANNEL_SIZE {
+            hub.publish(ClientCommand::Send(stock.clone()));
+        }
+        assert_eq!(stock_rx.len(), CHANNEL_SIZE);
+
+        let rename = CorporateAction::rename("FB", "META", 1);
+        hub.publish(ClientCommand::Action(rename.clone()));
+        let Ok(ClientCommand::Action(action)) = control_rx.try_recv() else {
+            panic!("rename expected on the control queue");
+        };
+        assert_eq!(action, rename);
+    }
+}
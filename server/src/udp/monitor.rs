@@ -1,8 +1,9 @@
-use crate::udp::client::ClientCommand;
-use crossbeam::channel::Sender;
-use log::{debug, error};
+use crate::metrics::StopReason;
+use crate::udp::registry::ClientRegistry;
+use log::{debug, error, warn};
 use parking_lot::Mutex;
-use std::collections::HashMap;
+use quotes::{Envelope, LEGACY_PING, LEGACY_PONG, Message};
+use std::collections::{HashMap, HashSet};
 use std::net::{SocketAddr, UdpSocket};
 use std::process::exit;
 use std::sync::{Arc, mpsc};
@@ -10,19 +11,20 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(5);
+/// Room for a ping envelope
+const PING_BUFFER_SIZE: usize = 256;
 
 type KeepAliveHolder = Arc<Mutex<HashMap<SocketAddr, Instant>>>;
 
 pub(crate) struct ClientsMonitor {
     socket: UdpSocket,
     clients: KeepAliveHolder,
+    /// Clients warned about their deprecated raw `PING`
+    legacy: HashSet<SocketAddr>,
 }
 
 impl ClientsMonitor {
-    pub(crate) fn run(
-        socket: UdpSocket,
-        stop_tx: Sender<ClientCommand>,
-    ) -> mpsc::Sender<SocketAddr> {
+    pub(crate) fn run(socket: UdpSocket, registry: ClientRegistry) -> mpsc::Sender<SocketAddr> {
         let (tx, rx) = mpsc::channel::<SocketAddr>();
         let clients_holder = Arc::new(Mutex::new(HashMap::new()));
         let mut monitoring = Self::new(socket, Arc::clone(&clients_holder));
@@ -53,14 +55,14 @@ impl ClientsMonitor {
                             KEEPALIVE_INTERVAL.as_secs()
                         );
                         to_remove.push(*k);
-                        if stop_tx.send(ClientCommand::Stop(*k)).is_err() {
-                            error!("Stop channel is closed!");
-                            exit(-1);
-                        }
                     }
                 }
+                for k in &to_remove {
+                    holder.remove(k);
+                }
+                drop(holder);
                 for k in to_remove {
-                    holder.remove(&k);
+                    registry.stop(k, StopReason::Keepalive);
                 }
             }
         });
@@ -68,20 +70,23 @@ impl ClientsMonitor {
     }
 
     fn new(socket: UdpSocket, clients: KeepAliveHolder) -> Self {
-        Self { socket, clients }
+        Self {
+            socket,
+            clients,
+            legacy: HashSet::new(),
+        }
     }
 
     fn start(&mut self) {
         loop {
-            let mut buffer = [0u8; 6];
+            let mut buffer = [0u8; PING_BUFFER_SIZE];
             match self.socket.recv_from(&mut buffer) {
                 Ok((size, addr)) => {
-                    if size != 4 || String::from_utf8_lossy(&buffer[..size]) != "PING" {
+                    let Some(pong) = self.pong(&buffer[..size], addr) else {
                         continue;
-                    }
-                    let mut clients = self.clients.lock();
-                    clients.insert(addr, Instant::now());
-                    if let Err(e) = self.socket.send_to(b"PONG", addr) {
+                    };
+                    self.clients.lock().insert(addr, Instant::now());
+                    if let Err(e) = self.socket.send_to(&pong, addr) {
                         error!("Server disconnected: {}", e);
                         exit(-1);
                     }
@@ -92,4 +97,49 @@ impl ClientsMonitor {
             }
         }
     }
+    /// Answer to a ping, `None` for any other datagram. A raw `PING` of an old client
+    /// gets a raw `PONG`
+    fn pong(&mut self, datagram: &[u8], addr: SocketAddr) -> Option<Vec<u8>> {
+        if datagram == LEGACY_PING {
+            if self.legacy.insert(addr) {
+                warn!(
+                    "{} sends the deprecated raw PING, it should switch to ping envelopes",
+                    addr
+                );
+            }
+            return Some(LEGACY_PONG.to_vec());
+        }
+        let ping = match Envelope::parse(datagram) {
+            Ok(ping) if ping.message == Message::Ping => ping,
+            Ok(_) => return None,
+            Err(e) => {
+                debug!("Invalid datagram from {}: {}", addr, e);
+                return None;
+            }
+        };
+        serde_json::to_vec(&Envelope::new(ping.sequence, Message::Pong)).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pong() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut monitor = ClientsMonitor::new(socket, Arc::new(Mutex::new(HashMap::new())));
+        let addr = SocketAddr::from(([127, 0, 0, 1], 9460));
+
+        let ping = serde_json::to_vec(&Envelope::new(7, Message::Ping)).unwrap();
+        let pong = monitor.pong(&ping, addr).unwrap();
+        assert_eq!(Envelope::parse(&pong), Ok(Envelope::new(7, Message::Pong)));
+
+        assert_eq!(monitor.pong(LEGACY_PING, addr), Some(LEGACY_PONG.to_vec()));
+        assert!(monitor.legacy.contains(&addr));
+
+        let pong = serde_json::to_vec(&Envelope::new(7, Message::Pong)).unwrap();
+        assert_eq!(monitor.pong(&pong, addr), None);
+        assert_eq!(monitor.pong(b"PINGS", addr), None);
+    }
 }
@@ -0,0 +1,233 @@
+use crate::metrics::{METRICS, StopReason};
+use crate::udp::client::{Client, ClientCommand};
+use crate::udp::hub::Hub;
+use crossbeam::channel::Sender;
+use log::{debug, error, info, warn};
+use parking_lot::Mutex;
+use quotes::StreamKind;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+struct ClientHandle {
+    control_tx: Sender<ClientCommand>,
+    thread: JoinHandle<()>,
+}
+
+/// Owns the control handle of every running `Client`, keyed by its UDP address
+#[derive(Clone)]
+pub(crate) struct ClientRegistry {
+    hub: Hub,
+    clients: Arc<Mutex<HashMap<SocketAddr, ClientHandle>>>,
+}
+
+impl ClientRegistry {
+    pub(crate) fn new(hub: Hub) -> Self {
+        Self {
+            hub,
+            clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Starts a worker streaming to `address`, or replaces the tickers and the kind of
+    /// the running one. A worker that has exited is replaced by a new one
+    pub(crate) fn start(
+        &self,
+        socket: UdpSocket,
+        address: SocketAddr,
+        tickers: HashSet<String>,
+        kind: StreamKind,
+    ) -> io::Result<()> {
+        let mut clients = self.clients.lock();
+        if let Some(handle) = clients.get(&address) {
+            let command = ClientCommand::Replace(tickers.clone(), kind);
+            if handle.control_tx.send(command).is_ok() {
+                return Ok(());
+            }
+            warn!("subscriber {} worker has exited, restarting it", address);
+            if let Some(handle) = clients.remove(&address)
+                && handle.thread.join().is_err()
+            {
+                error!("subscriber {} worker panicked", address);
+            }
+        }
+        let (control_tx, control_rx) = crossbeam::channel::unbounded();
+        let stock_rx = self.hub.subscribe(address, control_tx.clone());
+        let thread = Client::run(socket, address, tickers, kind, stock_rx, control_rx)?;
+        clients.insert(address, ClientHandle { control_tx, thread });
+        METRICS.client_started();
+        info!("subscriber {} started", address);
+        Ok(())
+    }
+
+    /// Delivers a control command to the worker streaming to `address`
+    pub(crate) fn send(&self, address: SocketAddr, command: ClientCommand) -> bool {
+        match self.clients.lock().get(&address) {
+            Some(handle) => handle.control_tx.send(command).is_ok(),
+            None => {
+                debug!("subscriber {} is not registered", address);
+                false
+            }
+        }
+    }
+
+    /// Stops the worker streaming to `address`, returns `false` if there is none
+    pub(crate) fn stop(&self, address: SocketAddr, reason: StopReason) -> bool {
+        let Some(handle) = self.clients.lock().remove(&address) else {
+            debug!("subscriber {} is not registered", address);
+            return false;
+        };
+        self.hub.unsubscribe(address);
+        Self::stop_handle(address, handle, reason);
+        true
+    }
+
+    pub(crate) fn stop_all(&self, reason: StopReason) {
+        let clients: Vec<_> = self.clients.lock().drain().collect();
+        for (address, handle) in clients {
+            self.hub.unsubscribe(address);
+            Self::stop_handle(address, handle, reason);
+        }
+    }
+
+    fn stop_handle(address: SocketAddr, handle: ClientHandle, reason: StopReason) {
+        let _ = handle.control_tx.send(ClientCommand::Stop(reason));
+        if handle.thread.join().is_err() {
+            error!("subscriber {} worker panicked", address);
+        }
+        METRICS.client_stopped(reason);
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.clients.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::udp::test_helpers::receive_event;
+    use crate::variables::CHANNEL_SIZE;
+    use quotes::Price;
+    use quotes::{BookQuote, MarketEvent, StockQuote};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_stop_targets_only_given_client() {
+        let (tx, rx) = crossbeam::channel::bounded(CHANNEL_SIZE);
+        let registry = ClientRegistry::new(Hub::run(rx, vec![]));
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let second = UdpSocket::bind("127.0.0.1:0").unwrap();
+        second
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let tickers = HashSet::from([String::from("AAPL")]);
+        for socket in [&first, &second] {
+            registry
+                .start(
+                    server.try_clone().unwrap(),
+                    socket.local_addr().unwrap(),
+                    tickers.clone(),
+                    StreamKind::TRADES,
+                )
+                .unwrap();
+        }
+
+        assert!(registry.stop(first.local_addr().unwrap(), StopReason::Keepalive));
+        assert!(!registry.stop(first.local_addr().unwrap(), StopReason::Keepalive));
+        assert_eq!(registry.len(), 1);
+
+        let stock = StockQuote::new("AAPL", Price::from_cents(100), 100);
+        tx.send(ClientCommand::Send(stock.clone())).unwrap();
+        let result = receive_event(&second);
+        assert_eq!(result, MarketEvent::Quote(stock));
+
+        registry.stop_all(StopReason::Shutdown);
+        assert_eq!(registry.len(), 0);
+    }
+
+    #[test]
+    fn test_stream_again_updates_tickers_in_place() {
+        let (tx, rx) = crossbeam::channel::bounded(CHANNEL_SIZE);
+        let registry = ClientRegistry::new(Hub::run(rx, vec![]));
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        let address = socket.local_addr().unwrap();
+
+        registry
+            .start(
+                server.try_clone().unwrap(),
+                address,
+                HashSet::from([String::from("AAPL")]),
+                StreamKind::TRADES,
+            )
+            .unwrap();
+        registry
+            .start(
+                server.try_clone().unwrap(),
+                address,
+                HashSet::from([String::from("MSFT")]),
+                "both".parse().unwrap(),
+            )
+            .unwrap();
+        assert_eq!(registry.len(), 1);
+        thread::sleep(Duration::from_millis(100));
+
+        let apple = StockQuote::new("AAPL", Price::from_cents(100), 100);
+        let microsoft = StockQuote::new("MSFT", Price::from_cents(200), 200);
+        tx.send(ClientCommand::Send(apple)).unwrap();
+        tx.send(ClientCommand::Send(microsoft.clone())).unwrap();
+        let (bid, ask) = (Price::from_cents(199), Price::from_cents(201));
+        let book = BookQuote::new("MSFT", bid, 100, ask, 100, microsoft.timestamp);
+        tx.send(ClientCommand::Book(book.clone())).unwrap();
+
+        let result = receive_event(&socket);
+        assert_eq!(result, MarketEvent::Quote(microsoft));
+        let result = receive_event(&socket);
+        assert_eq!(result, MarketEvent::Book(book));
+        let mut buffer = [0u8; 2048];
+        assert!(socket.recv(&mut buffer).is_err());
+    }
+
+    #[test]
+    fn test_stream_again_restarts_exited_worker() {
+        let (tx, rx) = crossbeam::channel::bounded(CHANNEL_SIZE);
+        let registry = ClientRegistry::new(Hub::run(rx, vec![]));
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let address = socket.local_addr().unwrap();
+
+        let (control_tx, _) = crossbeam::channel::unbounded();
+        let thread = thread::spawn(|| {});
+        let handle = ClientHandle { control_tx, thread };
+        registry.clients.lock().insert(address, handle);
+
+        registry
+            .start(
+                server,
+                address,
+                HashSet::from([String::from("AAPL")]),
+                StreamKind::TRADES,
+            )
+            .unwrap();
+        assert_eq!(registry.len(), 1);
+        thread::sleep(Duration::from_millis(100));
+
+        let stock = StockQuote::new("AAPL", Price::from_cents(100), 100);
+        tx.send(ClientCommand::Send(stock.clone())).unwrap();
+        assert_eq!(receive_event(&socket), MarketEvent::Quote(stock));
+        registry.stop_all(StopReason::Shutdown);
+    }
+}
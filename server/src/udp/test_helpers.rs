@@ -0,0 +1,17 @@
+use quotes::{Envelope, MarketEvent, Message};
+use std::net::UdpSocket;
+
+/// The next envelope received by `socket`
+pub(crate) fn receive(socket: &UdpSocket) -> Envelope {
+    let mut buffer = [0u8; 2048];
+    let len = socket.recv(&mut buffer).unwrap();
+    Envelope::parse(&buffer[..len]).unwrap()
+}
+
+/// Market data of the next envelope received by `socket`
+pub(crate) fn receive_event(socket: &UdpSocket) -> MarketEvent {
+    match receive(socket).message {
+        Message::Event(event) => event,
+        message => panic!("market data expected, got {:?}", message),
+    }
+}
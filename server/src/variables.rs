@@ -1 +1,4 @@
+use std::time::Duration;
+
 pub const CHANNEL_SIZE: usize = 128;
+pub const METRICS_INTERVAL: Duration = Duration::from_secs(60);